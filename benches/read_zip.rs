@@ -89,20 +89,18 @@ where
         .file_names()
         .filter_map(|c| {
             if !c.ends_with('/') {
-                Some(String::from(&*c))
+                Some(String::from(c))
             } else {
                 None
             }
         })
         .collect();
-    let mut files = files.into_iter();
 
-    while let Some(file) = files.next() {
+    if let Some(file) = files.first() {
         let mut data = Vec::new();
-        let mut f = archive.by_name(&file).unwrap();
+        let mut f = archive.by_name(file).unwrap();
         f.read_to_end(&mut data).unwrap();
         buffer.push(data);
-        break;
     }
 }
 
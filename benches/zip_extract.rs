@@ -4,10 +4,15 @@ use {
         async_executor::SmolExecutor, criterion_group, criterion_main, BenchmarkId, Criterion,
     },
     libzip_rs::{
-        error::{ZipError, ZipResult},
+        error::ZipResult,
+        write::{FileOptions, ZipWriter},
         ZipArchive as AsyncZipArchive,
     },
-    smol::{fs::File as AsyncFile, io::BufReader, stream::StreamExt},
+    smol::{
+        fs::File as AsyncFile,
+        io::{BufReader, Cursor},
+        stream::StreamExt,
+    },
     std::{
         fs::{read_dir, File},
         io::Read,
@@ -51,15 +56,14 @@ where
         .file_names()
         .filter_map(|c| {
             if !c.ends_with('/') {
-                Some(String::from(&*c))
+                Some(String::from(c))
             } else {
                 None
             }
         })
         .collect();
-    let mut files = files.into_iter();
 
-    while let Some(file) = files.next() {
+    for file in files {
         let mut data = Vec::new();
         let mut f = archive.by_name(&file).unwrap();
         f.read_to_end(&mut data).unwrap();
@@ -99,6 +103,65 @@ where
     }
 }
 
+async fn extract_libzip_seeking<P>(path: P)
+where
+    P: AsRef<Path>,
+{
+    let mut reader = AsyncFile::open(path).await.unwrap();
+    let mut archive = AsyncZipArchive::new(&mut reader).await.unwrap();
+    archive.set_read_ahead(0);
+    let mut buffer = Vec::with_capacity(archive.len());
+    let mut iter = archive.stream();
+
+    while let Some(file) = iter.next().await {
+        let data = file.unwrap().extract().await.unwrap();
+        buffer.push(data);
+    }
+}
+
+fn many_entries_archive() -> PathBuf {
+    let path = std::env::temp_dir().join("libananse-bench-many-entries.zip");
+    smol::block_on(async {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for idx in 0..4096 {
+            let data = format!("entry {idx} ").repeat(idx % 64 + 1);
+            writer
+                .write_file(
+                    format!("dir{}/file{idx}.txt", idx % 16),
+                    data.as_bytes(),
+                    FileOptions::new(),
+                )
+                .await
+                .unwrap();
+        }
+        let archive = writer.finish().await.unwrap().into_inner();
+        std::fs::write(&path, archive).unwrap();
+    });
+    path
+}
+
+fn extract_sequential(b: &mut Criterion) {
+    let path = many_entries_archive();
+
+    let mut group = b.benchmark_group("Zip Extract Many Entries");
+    group.sample_size(10);
+    group.bench_with_input(
+        BenchmarkId::new("Read-ahead stream", 4096),
+        &path,
+        |bench, path| bench.to_async(SmolExecutor).iter(|| extract_libzip(path)),
+    );
+    group.bench_with_input(
+        BenchmarkId::new("Seek per entry stream", 4096),
+        &path,
+        |bench, path| {
+            bench
+                .to_async(SmolExecutor)
+                .iter(|| extract_libzip_seeking(path))
+        },
+    );
+    group.finish();
+}
+
 fn extract(b: &mut Criterion) {
     let path = "zip/tests";
     let paths = recursive_read(path).unwrap();
@@ -112,11 +175,7 @@ fn extract(b: &mut Criterion) {
         group.bench_with_input(
             BenchmarkId::new("LibAnanse Zip", &id),
             &path,
-            |bench, reader| {
-                bench
-                    .to_async(SmolExecutor)
-                    .iter(|| extract_libzip(reader))
-            },
+            |bench, reader| bench.to_async(SmolExecutor).iter(|| extract_libzip(reader)),
         );
         group.bench_with_input(BenchmarkId::new("Async_zip", &id), &path, |bench, path| {
             bench
@@ -130,5 +189,5 @@ fn extract(b: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, extract);
+criterion_group!(benches, extract, extract_sequential);
 criterion_main!(benches);
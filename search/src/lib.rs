@@ -81,54 +81,27 @@ where
         let pattern = pattern.as_ref();
         let (pat_len, space_len) = (pattern.len(), space.len());
 
-        if space_len == 0 {
+        if pat_len == 0 || space_len < pat_len {
             return None;
         }
 
+        // Shift lining the nearest later occurrence of a byte up with the window start.
         let table: HashMap<u8, usize> = pattern
             .iter()
             .enumerate()
-            .map(|(idx, b)| if idx == 0 { (*b, pat_len) } else { (*b, idx) })
+            .skip(1)
+            .rev()
+            .map(|(idx, b)| (*b, idx))
             .collect();
 
         let mut idx = space_len - pat_len;
-        space
-            .iter()
-            .find_map(|_| {
-                if let Some(hit) = table.get(&space[idx]) {
-                    let catch = pattern.iter().enumerate().find_map(|(sg_idx, sg_byte)| {
-                        let nxt = idx + sg_idx;
-                        if &space[nxt] == sg_byte {
-                            if sg_idx == 0 {
-                                Some(Some(idx))
-                            } else {
-                                None
-                            }
-                        } else {
-                            Some(None)
-                        }
-                    });
-                    match catch {
-                        Some(Some(value)) => Some(Some(value)),
-                        _ => {
-                            if let Some(sub) = idx.checked_sub(*hit) {
-                                idx = sub;
-                                None
-                            } else {
-                                Some(None)
-                            }
-                        }
-                    }
-                } else {
-                    if let Some(sub) = idx.checked_sub(pat_len) {
-                        idx = sub;
-                        None
-                    } else {
-                        Some(None)
-                    }
-                }
-            })
-            .flatten()
+        loop {
+            if &space[idx..idx + pat_len] == pattern {
+                return Some(idx);
+            }
+            let shift = table.get(&space[idx]).copied().unwrap_or(pat_len);
+            idx = idx.checked_sub(shift)?;
+        }
     }
 
     fn search_all(&self, pattern: &O) -> Vec<usize> {
@@ -197,6 +170,16 @@ fn reverse_search() {
     assert_eq!(Some(10), space.rsearch(&pat))
 }
 
+#[test]
+fn reverse_search_multiple_bytes() {
+    let space = b"PK\x06\x06....PK\x06\x07....PK\x05\x06";
+    assert_eq!(Some(0), space.rsearch(b"PK\x06\x06"));
+    assert_eq!(Some(8), space.rsearch(b"PK\x06\x07"));
+    assert_eq!(Some(16), space.rsearch(b"PK\x05\x06"));
+    assert_eq!(None, space.rsearch(b"PK\x01\x02"));
+    assert_eq!(Some(3), b"abaaba".rsearch(b"ab"));
+}
+
 #[test]
 fn search() {
     let space = [0, 1, 2, 3, 4, 5, 6, 7, 6, 9, 5, 6, 7, 8];
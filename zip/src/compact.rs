@@ -0,0 +1,134 @@
+use {
+    crate::{
+        error::ZipResult,
+        glob::{CaseSensitivity, Pattern},
+        index::{CompactStorage, DuplicatePolicy},
+        path::ZipPath,
+        specs::ZipEntry,
+        verify::VerifyReport,
+        ZipArchive, ZipFile,
+    },
+    futures_lite::{
+        io::{AsyncRead, AsyncSeek},
+        stream::Stream,
+    },
+    std::{borrow::Cow, ffi::OsStr},
+};
+
+/// An archive whose central directory is kept close to its on-disk size: the
+/// fixed part of every header in a flat array and names, zip64 blocks and
+/// comments in one shared buffer. Entries are decoded on every access, so they
+/// are returned owned rather than borrowed.
+pub struct CompactZipArchive<R> {
+    archive: ZipArchive<R, CompactStorage>,
+}
+
+impl<R> CompactZipArchive<R> {
+    pub fn len(&self) -> usize {
+        self.archive.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.archive.is_empty()
+    }
+
+    pub fn comment(&self) -> &Option<String> {
+        self.archive.comment()
+    }
+
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.archive.duplicate_policy()
+    }
+
+    pub fn set_duplicate_policy(&mut self, policy: DuplicatePolicy) {
+        self.archive.set_duplicate_policy(policy)
+    }
+
+    pub fn indices_of<S>(&self, path: S) -> Vec<usize>
+    where
+        S: AsRef<OsStr>,
+    {
+        self.archive.indices_of(path)
+    }
+
+    pub fn duplicates(&self) -> Vec<(ZipPath, Vec<usize>)> {
+        self.archive.duplicates()
+    }
+
+    pub fn entries(&self) -> impl ExactSizeIterator<Item = Cow<'_, ZipEntry>> {
+        self.archive.entries.values()
+    }
+
+    pub fn entry_by_name<S>(&self, path: S) -> Option<Cow<'_, ZipEntry>>
+    where
+        S: AsRef<OsStr>,
+    {
+        self.archive
+            .entries
+            .get_index(self.archive.index_of(path.as_ref())?)
+    }
+
+    pub fn entry_by_index(&self, index: usize) -> Option<Cow<'_, ZipEntry>> {
+        self.archive.entries.get_index(index)
+    }
+
+    pub fn glob(
+        &self,
+        pattern: &str,
+        case: CaseSensitivity,
+    ) -> ZipResult<impl Iterator<Item = Cow<'_, ZipEntry>>> {
+        let pattern = Pattern::with_case(pattern, case)?;
+        Ok(self.entries_matching(pattern))
+    }
+
+    pub fn entries_matching(&self, pattern: Pattern) -> impl Iterator<Item = Cow<'_, ZipEntry>> {
+        self.entries()
+            .filter(move |entry| pattern.matches(entry.name().as_os_str()))
+    }
+}
+
+impl<R> CompactZipArchive<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    pub async fn new(reader: R) -> ZipResult<Self> {
+        let archive = ZipArchive::open(reader).await?;
+        Ok(Self { archive })
+    }
+
+    pub async fn file_by_name<S>(&mut self, path: S) -> ZipResult<ZipFile>
+    where
+        S: AsRef<OsStr>,
+    {
+        self.archive.file_by_name(path).await
+    }
+
+    pub async fn file_by_index(&mut self, index: usize) -> ZipResult<ZipFile> {
+        self.archive.file_by_index(index).await
+    }
+
+    pub async fn verify(&mut self) -> VerifyReport {
+        self.archive.verify().await
+    }
+
+    pub fn stream(&mut self) -> impl Stream<Item = ZipResult<ZipFile>> + Unpin + '_ {
+        self.archive.stream()
+    }
+
+    pub fn stream_matching(
+        &mut self,
+        pattern: Pattern,
+    ) -> impl Stream<Item = ZipResult<ZipFile>> + Unpin + '_ {
+        self.archive.stream_matching(pattern)
+    }
+
+    pub fn stream_filter<'a, F>(
+        &'a mut self,
+        filter: F,
+    ) -> impl Stream<Item = ZipResult<ZipFile>> + Unpin + 'a
+    where
+        F: FnMut(&ZipEntry) -> bool + 'a,
+    {
+        self.archive.stream_filter(filter)
+    }
+}
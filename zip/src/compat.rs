@@ -0,0 +1,86 @@
+use {
+    crate::{error::ZipResult, read::volume::MultiVolumeReader, write::ZipWriter, ZipArchive},
+    tokio::io::{AsyncRead, AsyncSeek, AsyncWrite},
+    tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt},
+};
+
+pub use tokio_util::compat::Compat;
+
+impl<R> ZipArchive<Compat<R>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    pub async fn with_tokio(reader: R) -> ZipResult<Self> {
+        Self::new(reader.compat()).await
+    }
+}
+
+impl<R> ZipArchive<MultiVolumeReader<Compat<R>>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    pub async fn with_tokio_volumes(volumes: Vec<R>) -> ZipResult<Self> {
+        Self::with_volumes(volumes.into_iter().map(|volume| volume.compat()).collect()).await
+    }
+}
+
+impl<W> ZipWriter<Compat<W>>
+where
+    W: AsyncWrite + Unpin,
+{
+    pub fn with_tokio(writer: W) -> Self {
+        Self::new(writer.compat_write())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            testing::{build_archive, build_volumes},
+            write::{FileOptions, ZipWriter},
+            ZipArchive,
+        },
+        futures_lite::future::block_on,
+        std::io::Cursor,
+    };
+
+    #[test]
+    fn use_tokio_reader_and_writer() {
+        block_on(async {
+            let files: [(&str, &[u8]); 2] = [("a.txt", b"alpha"), ("b/c.txt", b"charlie")];
+
+            let mut zip = ZipArchive::with_tokio(Cursor::new(build_archive(&files)))
+                .await
+                .unwrap();
+            for (name, data) in files {
+                assert_eq!(&*zip.file_by_name(name).await.unwrap(), data);
+            }
+
+            let volumes = build_volumes(&files, Some(1))
+                .into_iter()
+                .map(Cursor::new)
+                .collect();
+            let mut zip = ZipArchive::with_tokio_volumes(volumes).await.unwrap();
+            assert_eq!(&*zip.file_by_index(1).await.unwrap(), b"charlie");
+
+            let mut writer = ZipWriter::with_tokio(Vec::new());
+            writer
+                .write_file("a.txt", b"alpha", FileOptions::new())
+                .await
+                .unwrap();
+            let archive = writer.finish().await.unwrap().into_inner();
+            let mut zip = ZipArchive::with_tokio(Cursor::new(archive)).await.unwrap();
+            assert_eq!(
+                &*zip
+                    .file_by_name("a.txt")
+                    .await
+                    .unwrap()
+                    .extract()
+                    .await
+                    .unwrap(),
+                b"alpha"
+            );
+        })
+    }
+}
@@ -1,6 +1,6 @@
 use crate::error::{ZipError, ZipResult};
 
-#[derive(Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub struct ZipDateTime {
     pub year: u16,
     pub month: u8,
@@ -10,6 +10,48 @@ pub struct ZipDateTime {
     pub second: u8,
 }
 
+impl Default for ZipDateTime {
+    fn default() -> Self {
+        Self {
+            year: 1980,
+            month: 1,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0,
+        }
+    }
+}
+
+impl ZipDateTime {
+    /// Converts seconds since the Unix epoch (UTC), clamped to the 1980..=2107
+    /// range that MS-DOS timestamps can represent.
+    pub fn from_unix_timestamp(seconds: i64) -> Self {
+        let seconds = seconds.clamp(315_532_800, 4_354_819_198);
+        let (days, time) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (time / 3600) as u8,
+            minute: (time % 3600 / 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+}
+
 impl TryFrom<[u8; 4]> for ZipDateTime {
     type Error = ZipError;
 
@@ -18,11 +60,11 @@ impl TryFrom<[u8; 4]> for ZipDateTime {
         let date = u16::from_le_bytes(value[2..4].try_into()?);
 
         let year = ((date & 0xFE00) >> 9) + 1980;
-        let month = ((date & 0x1E0) >> 6) as u8;
+        let month = ((date & 0x1E0) >> 5) as u8;
         let day = (date & 0x1F) as u8;
         let hour = ((time & 0xF800) >> 11) as u8;
-        let minute = ((time & 0x7E6) >> 5) as u8;
-        let second = ((time & 0xF1) << 1) as u8;
+        let minute = ((time & 0x7E0) >> 5) as u8;
+        let second = ((time & 0x1F) << 1) as u8;
 
         Ok(Self {
             year,
@@ -34,3 +76,18 @@ impl TryFrom<[u8; 4]> for ZipDateTime {
         })
     }
 }
+
+impl From<ZipDateTime> for [u8; 4] {
+    fn from(value: ZipDateTime) -> Self {
+        let time =
+            (value.hour as u16) << 11 | (value.minute as u16) << 5 | (value.second as u16) >> 1;
+        let date = value.year.saturating_sub(1980).min(0x7F) << 9
+            | (value.month as u16 & 0xF) << 5
+            | value.day as u16 & 0x1F;
+
+        let mut buffer = [0; 4];
+        buffer[0..2].copy_from_slice(&time.to_le_bytes());
+        buffer[2..4].copy_from_slice(&date.to_le_bytes());
+        buffer
+    }
+}
@@ -0,0 +1,297 @@
+use {
+    crate::{error::ZipResult, path::ZipPath, specs::ZipEntry, ZipArchive},
+    futures_lite::io::{AsyncRead, AsyncSeek},
+    std::ffi::OsStr,
+};
+
+/// Pairs of lines compared at most, bounding the table to 16 MiB.
+const MAX_TABLE_CELLS: usize = 1 << 22;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Change {
+    Crc32,
+    Size,
+    Compression,
+    Attributes,
+    LastModified,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DiffLine {
+    Unchanged(String),
+    Removed(String),
+    Added(String),
+}
+
+#[derive(Debug)]
+pub struct Modification {
+    name: ZipPath,
+    changes: Vec<Change>,
+}
+
+#[derive(Debug, Default)]
+pub struct DiffReport {
+    added: Vec<ZipPath>,
+    removed: Vec<ZipPath>,
+    modified: Vec<Modification>,
+}
+
+impl Modification {
+    pub fn name(&self) -> &ZipPath {
+        &self.name
+    }
+
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+}
+
+impl DiffReport {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    pub fn added(&self) -> &[ZipPath] {
+        &self.added
+    }
+
+    pub fn removed(&self) -> &[ZipPath] {
+        &self.removed
+    }
+
+    pub fn modified(&self) -> &[Modification] {
+        &self.modified
+    }
+}
+
+impl<R> ZipArchive<R> {
+    /// Compares central directory metadata only; no entry data is read.
+    pub fn diff<O>(&self, other: &ZipArchive<O>) -> DiffReport {
+        let mut report = DiffReport::default();
+        for (index, entry) in self.entries.values().enumerate() {
+            let name = &entry.file_name;
+            if self.index_of(name) != Some(index) {
+                continue;
+            }
+            match other.entry_by_name(name.as_os_str()) {
+                Some(changed) => {
+                    let changes = compare(&entry, changed);
+                    if !changes.is_empty() {
+                        report.modified.push(Modification {
+                            name: name.clone(),
+                            changes,
+                        });
+                    }
+                }
+                None => report.removed.push(name.clone()),
+            }
+        }
+        report.added = other
+            .entries
+            .values()
+            .enumerate()
+            .filter(|(index, entry)| {
+                other.index_of(&entry.file_name) == Some(*index)
+                    && self.index_of(&entry.file_name).is_none()
+            })
+            .map(|(_index, entry)| entry.file_name.clone())
+            .collect();
+        report
+    }
+}
+
+impl<R> ZipArchive<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    /// Line diff of an entry present in both archives, or `None` when either
+    /// side is not valid UTF-8. Past a few million pairs of differing lines,
+    /// the differing part is reported as removed then added as a whole.
+    pub async fn diff_text<O, S>(
+        &mut self,
+        other: &mut ZipArchive<O>,
+        name: S,
+    ) -> ZipResult<Option<Vec<DiffLine>>>
+    where
+        O: AsyncRead + AsyncSeek + Unpin,
+        S: AsRef<OsStr>,
+    {
+        let old = self.file_by_name(name.as_ref()).await?.extract().await?;
+        let new = other.file_by_name(name.as_ref()).await?.extract().await?;
+        match (String::from_utf8(old), String::from_utf8(new)) {
+            (Ok(old), Ok(new)) => Ok(Some(diff_lines(&old, &new))),
+            _ => Ok(None),
+        }
+    }
+}
+
+fn compare(old: &ZipEntry, new: &ZipEntry) -> Vec<Change> {
+    let mut changes = Vec::new();
+    if old.crc32 != new.crc32 {
+        changes.push(Change::Crc32);
+    }
+    if old.uncompressed_size != new.uncompressed_size {
+        changes.push(Change::Size);
+    }
+    if old.compression != new.compression {
+        changes.push(Change::Compression);
+    }
+    if old.external_attribute != new.external_attribute {
+        changes.push(Change::Attributes);
+    }
+    if old.last_mod_datetime != new.last_mod_datetime {
+        changes.push(Change::LastModified);
+    }
+    changes
+}
+
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_middle, new_middle) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut lines: Vec<DiffLine> = old[..prefix]
+        .iter()
+        .map(|line| DiffLine::Unchanged(line.to_string()))
+        .collect();
+    let width = new_middle.len() + 1;
+    match (old_middle.len() + 1).checked_mul(width) {
+        Some(cells) if cells <= MAX_TABLE_CELLS => {
+            lines.extend(diff_middle(old_middle, new_middle));
+        }
+        _ => {
+            lines.extend(
+                old_middle
+                    .iter()
+                    .map(|line| DiffLine::Removed(line.to_string())),
+            );
+            lines.extend(
+                new_middle
+                    .iter()
+                    .map(|line| DiffLine::Added(line.to_string())),
+            );
+        }
+    }
+    lines.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| DiffLine::Unchanged(line.to_string())),
+    );
+    lines
+}
+
+fn diff_middle(old_middle: &[&str], new_middle: &[&str]) -> Vec<DiffLine> {
+    // Longest common subsequence lengths of every pair of suffixes.
+    let width = new_middle.len() + 1;
+    let mut lengths = vec![0u32; (old_middle.len() + 1) * width];
+    for i in (0..old_middle.len()).rev() {
+        for j in (0..new_middle.len()).rev() {
+            lengths[i * width + j] = if old_middle[i] == new_middle[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old_middle.len() || j < new_middle.len() {
+        if i < old_middle.len() && j < new_middle.len() && old_middle[i] == new_middle[j] {
+            lines.push(DiffLine::Unchanged(old_middle[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < old_middle.len()
+            && (j == new_middle.len() || lengths[(i + 1) * width + j] >= lengths[i * width + j + 1])
+        {
+            lines.push(DiffLine::Removed(old_middle[i].to_string()));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new_middle[j].to_string()));
+            j += 1;
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{diff_lines, Change, DiffLine},
+        crate::{testing::build_archive, ZipArchive},
+        smol::io::Cursor,
+    };
+
+    #[test]
+    fn replace_large_differences_whole() {
+        let old: String = (0..3000).map(|idx| format!("old {idx}\n")).collect();
+        let new: String = (0..3000).map(|idx| format!("new {idx}\n")).collect();
+        let lines = diff_lines(&format!("same\n{old}end"), &format!("same\n{new}end"));
+
+        assert_eq!(lines.len(), 6002);
+        assert_eq!(lines[0], DiffLine::Unchanged("same".into()));
+        assert!(lines[1..3001]
+            .iter()
+            .all(|line| matches!(line, DiffLine::Removed(_))));
+        assert!(lines[3001..6001]
+            .iter()
+            .all(|line| matches!(line, DiffLine::Added(_))));
+        assert_eq!(lines[6001], DiffLine::Unchanged("end".into()));
+    }
+
+    #[test]
+    fn diff_archives() {
+        smol::block_on(async {
+            let old = build_archive(&[
+                ("a.txt", b"alpha"),
+                ("b.txt", b"one\ntwo\nthree\n"),
+                ("c.txt", b"gone"),
+                ("e.bin", &[0xFF, 0x00]),
+            ]);
+            let new = build_archive(&[
+                ("a.txt", b"alpha"),
+                ("b.txt", b"one\n2\nthree\nfour\n"),
+                ("d.txt", b"new"),
+                ("e.bin", &[0xFE, 0x00]),
+            ]);
+            let mut old = ZipArchive::new(Cursor::new(old)).await.unwrap();
+            let mut new = ZipArchive::new(Cursor::new(new)).await.unwrap();
+
+            let report = old.diff(&new);
+            assert!(!report.is_empty());
+            assert_eq!(report.added(), ["d.txt".into()]);
+            assert_eq!(report.removed(), ["c.txt".into()]);
+            let modified = report.modified();
+            assert_eq!(modified.len(), 2);
+            assert_eq!(modified[0].name().as_os_str(), "b.txt");
+            assert_eq!(modified[0].changes(), [Change::Crc32, Change::Size]);
+            assert_eq!(modified[1].changes(), [Change::Crc32]);
+            assert!(old.diff(&old).is_empty());
+
+            let lines = old.diff_text(&mut new, "b.txt").await.unwrap().unwrap();
+            assert_eq!(
+                lines,
+                [
+                    DiffLine::Unchanged("one".into()),
+                    DiffLine::Removed("two".into()),
+                    DiffLine::Added("2".into()),
+                    DiffLine::Unchanged("three".into()),
+                    DiffLine::Added("four".into()),
+                ]
+            );
+            assert!(old.diff_text(&mut new, "e.bin").await.unwrap().is_none());
+            assert!(old.diff_text(&mut new, "c.txt").await.is_err());
+        })
+    }
+}
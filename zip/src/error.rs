@@ -1,19 +1,25 @@
+use crate::compression::Compression;
 use std::array::TryFromSliceError;
 use std::convert::Infallible;
 use std::io;
+use std::path::PathBuf;
 
 pub type ZipResult<T> = Result<T, ZipError>;
 
 #[derive(Debug)]
 pub enum ZipError {
     AttributeCompatibilityNotSupported,
+    CompressionDisabled(Compression),
     CompressionNotSupported,
     FeatureNotSupported(Box<str>),
     InvalidArchive(Box<str>),
+    InvalidPattern(Box<str>),
     IO(io::Error),
     MissingAttribute,
+    NestingLimitExceeded(usize),
     SignatureNotFound(Box<str>),
     SliceArray(TryFromSliceError),
+    UnsafeSymlink(PathBuf),
     Infallible(Infallible),
     ZipUnicodeCommentExtraFieldInfoIncomplete,
     ZipUnicodePathInfoExtraFieldIncomplete,
@@ -0,0 +1,316 @@
+use {
+    crate::error::{ZipError, ZipResult},
+    std::ffi::OsStr,
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum CaseSensitivity {
+    #[default]
+    Sensitive,
+    Insensitive,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Literal(char),
+    AnyChar,
+    AnyString,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Segment {
+    AnyDepth,
+    Tokens(Vec<Token>),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Pattern {
+    source: Box<str>,
+    segments: Vec<Segment>,
+    case: CaseSensitivity,
+}
+
+impl CaseSensitivity {
+    pub(crate) fn eq_char(self, a: char, b: char) -> bool {
+        match self {
+            Self::Sensitive => a == b,
+            Self::Insensitive => a == b || a.to_lowercase().eq(b.to_lowercase()),
+        }
+    }
+
+    pub(crate) fn fold(self, string: &str) -> String {
+        match self {
+            Self::Sensitive => string.to_owned(),
+            Self::Insensitive => string.to_lowercase(),
+        }
+    }
+}
+
+impl Token {
+    fn matches(&self, c: char, case: CaseSensitivity) -> bool {
+        match self {
+            Self::Literal(literal) => case.eq_char(*literal, c),
+            Self::AnyChar | Self::AnyString => true,
+            Self::Class { negated, ranges } => {
+                let hit = |c: char| ranges.iter().any(|(low, high)| (*low..=*high).contains(&c));
+                let found = match case {
+                    CaseSensitivity::Sensitive => hit(c),
+                    CaseSensitivity::Insensitive => {
+                        hit(c) || c.to_lowercase().any(hit) || c.to_uppercase().any(hit)
+                    }
+                };
+                found != *negated
+            }
+        }
+    }
+}
+
+impl Segment {
+    fn parse(segment: &str) -> ZipResult<Self> {
+        if segment == "**" {
+            return Ok(Self::AnyDepth);
+        }
+
+        let mut tokens = Vec::new();
+        let mut chars = segment.chars();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                '?' => Token::AnyChar,
+                '*' => {
+                    if tokens.last() == Some(&Token::AnyString) {
+                        continue;
+                    }
+                    Token::AnyString
+                }
+                '\\' => match chars.next() {
+                    Some(escaped) => Token::Literal(escaped),
+                    None => Err(ZipError::InvalidPattern(
+                        "Pattern ends with an escape character".into(),
+                    ))?,
+                },
+                '[' => {
+                    let mut negated = false;
+                    let mut ranges = Vec::new();
+                    let mut closed = false;
+                    let mut first = true;
+
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '!' | '^' if first => negated = true,
+                            ']' if !first || !ranges.is_empty() => {
+                                closed = true;
+                                break;
+                            }
+                            _ => {
+                                let low = if c == '\\' {
+                                    chars.next().unwrap_or(c)
+                                } else {
+                                    c
+                                };
+                                let mut lookahead = chars.clone();
+                                if lookahead.next() == Some('-') {
+                                    match lookahead.next() {
+                                        Some(high) if high != ']' => {
+                                            chars = lookahead;
+                                            ranges.push((low, high));
+                                        }
+                                        _ => ranges.push((low, low)),
+                                    }
+                                } else {
+                                    ranges.push((low, low));
+                                }
+                            }
+                        }
+                        first = false;
+                    }
+
+                    if !closed {
+                        Err(ZipError::InvalidPattern(
+                            "Unterminated character class".into(),
+                        ))?
+                    }
+                    Token::Class { negated, ranges }
+                }
+                c => Token::Literal(c),
+            };
+            tokens.push(token);
+        }
+        Ok(Self::Tokens(tokens))
+    }
+
+    fn matches(tokens: &[Token], name: &[char], case: CaseSensitivity) -> bool {
+        let (mut t, mut n) = (0, 0);
+        let mut backtrack = None;
+
+        while n < name.len() {
+            match tokens.get(t) {
+                Some(Token::AnyString) => {
+                    backtrack = Some((t, n));
+                    t += 1;
+                }
+                Some(token) if token.matches(name[n], case) => {
+                    t += 1;
+                    n += 1;
+                }
+                _ => match backtrack {
+                    Some((star, from)) => {
+                        t = star + 1;
+                        n = from + 1;
+                        backtrack = Some((star, from + 1));
+                    }
+                    None => return false,
+                },
+            }
+        }
+        tokens[t..].iter().all(|token| *token == Token::AnyString)
+    }
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> ZipResult<Self> {
+        Self::with_case(pattern, CaseSensitivity::Sensitive)
+    }
+
+    pub fn with_case(pattern: &str, case: CaseSensitivity) -> ZipResult<Self> {
+        let segments = pattern
+            .trim_start_matches('/')
+            .trim_end_matches('/')
+            .split('/')
+            .map(Segment::parse)
+            .collect::<ZipResult<Vec<_>>>()?;
+
+        Ok(Self {
+            source: pattern.into(),
+            segments,
+            case,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn case(&self) -> CaseSensitivity {
+        self.case
+    }
+
+    pub fn matches<P>(&self, path: P) -> bool
+    where
+        P: AsRef<OsStr>,
+    {
+        let path = path.as_ref().to_string_lossy();
+        let path: Vec<Vec<char>> = path
+            .trim_start_matches('/')
+            .trim_end_matches('/')
+            .split('/')
+            .map(|segment| segment.chars().collect())
+            .collect();
+        self.matches_segments(&self.segments, &path)
+    }
+
+    fn matches_segments(&self, segments: &[Segment], path: &[Vec<char>]) -> bool {
+        match segments.split_first() {
+            None => path.is_empty(),
+            Some((Segment::AnyDepth, rest)) => {
+                (0..=path.len()).any(|skip| self.matches_segments(rest, &path[skip..]))
+            }
+            Some((Segment::Tokens(tokens), rest)) => match path.split_first() {
+                Some((name, path)) => {
+                    Segment::matches(tokens, name, self.case) && self.matches_segments(rest, path)
+                }
+                None => false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{CaseSensitivity, Pattern},
+        crate::{testing::build_archive, ZipArchive},
+        smol::{io::Cursor, stream::StreamExt},
+    };
+
+    #[test]
+    fn match_wildcards() {
+        let pattern = Pattern::new("textures/**/*.png").unwrap();
+        assert!(pattern.matches("textures/a.png"));
+        assert!(pattern.matches("textures/ui/icons/b.png"));
+        assert!(!pattern.matches("textures/ui/b.jpg"));
+        assert!(!pattern.matches("models/a.png"));
+
+        let pattern = Pattern::new("file?.[a-c]x[!0-9]").unwrap();
+        assert!(pattern.matches("file1.bxy"));
+        assert!(!pattern.matches("file12.bxy"));
+        assert!(!pattern.matches("file1.dxy"));
+        assert!(!pattern.matches("file1.bx5"));
+
+        let pattern = Pattern::new("*").unwrap();
+        assert!(pattern.matches("top.txt"));
+        assert!(!pattern.matches("nested/file.txt"));
+    }
+
+    #[test]
+    fn match_case_insensitive() {
+        let pattern = Pattern::with_case("Locale/FR/*.PO", CaseSensitivity::Insensitive).unwrap();
+        assert!(pattern.matches("locale/fr/messages.po"));
+        assert!(!Pattern::new("Locale/FR/*.PO")
+            .unwrap()
+            .matches("locale/fr/messages.po"));
+    }
+
+    #[test]
+    fn reject_invalid_pattern() {
+        assert!(Pattern::new("broken[abc").is_err());
+        assert!(Pattern::new("trailing\\").is_err());
+    }
+
+    #[test]
+    fn query_entries_by_pattern() {
+        smol::block_on(async {
+            let files: [(&str, &[u8]); 5] = [
+                ("textures/stone.png", b"stone"),
+                ("textures/ui/Button.PNG", b"button"),
+                ("textures/ui/readme.txt", b"readme"),
+                ("locale/fr/messages.po", b"bonjour"),
+                ("locale/fr-CA/messages.po", b"allo"),
+            ];
+            let mut zip = ZipArchive::new(Cursor::new(build_archive(&files)))
+                .await
+                .unwrap();
+
+            let names = |entries: Vec<&crate::ZipEntry>| -> Vec<String> {
+                entries
+                    .into_iter()
+                    .map(|entry| entry.name().to_string_lossy().into_owned())
+                    .collect()
+            };
+
+            let sensitive = zip.glob("textures/**/*.png", CaseSensitivity::Sensitive);
+            assert_eq!(names(sensitive.unwrap().collect()), ["textures/stone.png"]);
+            let insensitive = zip.glob("textures/**/*.png", CaseSensitivity::Insensitive);
+            assert_eq!(
+                names(insensitive.unwrap().collect()),
+                ["textures/stone.png", "textures/ui/Button.PNG"]
+            );
+
+            let prefixed = zip.entries_with_prefix("locale/fr", CaseSensitivity::Sensitive);
+            assert_eq!(prefixed.count(), 2);
+            let nested = zip.entries_in("LOCALE/FR", CaseSensitivity::Insensitive);
+            assert_eq!(names(nested.collect()), ["locale/fr/messages.po"]);
+
+            let pattern = Pattern::new("textures/ui/*").unwrap();
+            let mut stream = zip.stream_matching(pattern);
+            let mut data = Vec::new();
+            while let Some(file) = stream.next().await {
+                data.push(file.unwrap().data);
+            }
+            assert_eq!(data, [b"button".to_vec(), b"readme".to_vec()]);
+        })
+    }
+}
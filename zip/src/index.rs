@@ -0,0 +1,443 @@
+use {
+    crate::{
+        error::{ZipError, ZipResult},
+        specs::{attribute::AttributeCompatibility, extra_field::zip64_block, ZipEntry},
+    },
+    std::{
+        borrow::Cow,
+        collections::HashMap,
+        ffi::OsStr,
+        hash::{BuildHasher, RandomState},
+    },
+};
+
+const HEADER_SIZE: usize = 46;
+
+/// Which record a lookup by name returns when several share that name.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum DuplicatePolicy {
+    First,
+    /// The record that overwrites the others when extracting in order.
+    #[default]
+    Last,
+}
+
+impl DuplicatePolicy {
+    pub(crate) fn pick<I>(self, mut positions: I) -> Option<usize>
+    where
+        I: Iterator<Item = usize>,
+    {
+        match self {
+            DuplicatePolicy::First => positions.next(),
+            DuplicatePolicy::Last => positions.last(),
+        }
+    }
+}
+
+/// Every central directory record in order, with an open-addressing table of
+/// record indices for lookups by name. Records sharing a name are all kept.
+pub(crate) struct ZipIndex<S = Vec<ZipEntry>> {
+    storage: S,
+    table: Vec<u32>,
+    hasher: RandomState,
+}
+
+/// How an archive holds its central directory records. Only a `Vec<ZipEntry>`
+/// can lend its entries, so borrowing accessors exist for that storage alone.
+/// Sealed: `CompactZipArchive` uses the other one.
+pub trait Storage: sealed::Storage {}
+
+impl<S> Storage for S where S: sealed::Storage {}
+
+mod sealed {
+    use {
+        crate::{error::ZipResult, specs::ZipEntry},
+        std::borrow::Cow,
+    };
+
+    pub trait Storage {
+        fn with_capacity(capacity: usize) -> Self;
+
+        fn len(&self) -> usize;
+
+        /// Adds the central directory header starting at `value`.
+        fn push(&mut self, value: &[u8]) -> ZipResult<()>;
+
+        fn get(&self, index: usize) -> Option<Cow<'_, ZipEntry>>;
+
+        fn name(&self, index: usize) -> &[u8];
+    }
+}
+
+/// Central directory kept close to its on-disk size: the fixed part of every
+/// header in a flat array and names, zip64 blocks and comments in one shared
+/// buffer. Other extra fields are dropped.
+pub(crate) struct CompactStorage {
+    records: Vec<Record>,
+    strings: Vec<u8>,
+}
+
+struct Record {
+    header: [u8; HEADER_SIZE],
+    offset: u64,
+}
+
+impl ZipIndex {
+    pub(crate) fn as_slice(&self) -> &[ZipEntry] {
+        &self.storage
+    }
+}
+
+impl<S> ZipIndex<S>
+where
+    S: Storage,
+{
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            storage: S::with_capacity(capacity),
+            table: vec![0; (capacity * 2).next_power_of_two().max(8)],
+            hasher: RandomState::new(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.storage.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds the central directory header starting at `value`.
+    pub(crate) fn push_header(&mut self, value: &[u8]) -> ZipResult<()> {
+        self.storage.push(value)?;
+        self.place_last();
+        Ok(())
+    }
+
+    pub(crate) fn get_index(&self, index: usize) -> Option<Cow<'_, ZipEntry>> {
+        self.storage.get(index)
+    }
+
+    /// Indices of every record named `name`, in central directory order.
+    pub(crate) fn positions<'a>(&'a self, name: &'a OsStr) -> impl Iterator<Item = usize> + 'a {
+        self.positions_of(name.as_encoded_bytes())
+    }
+
+    /// Indices of the records sharing a name, grouped by name in the order of
+    /// their first record.
+    pub(crate) fn duplicates(&self) -> Vec<Vec<usize>> {
+        let mut groups: HashMap<&[u8], Vec<usize>> = HashMap::new();
+        for index in 0..self.len() {
+            groups.entry(self.name(index)).or_default().push(index);
+        }
+        let mut groups: Vec<Vec<usize>> = groups
+            .into_values()
+            .filter(|positions| positions.len() > 1)
+            .collect();
+        groups.sort_unstable();
+        groups
+    }
+
+    /// Records are placed in index order and never removed, so a later record
+    /// always sits further along the probe sequence than an earlier one with the
+    /// same name: probing yields matches in central directory order.
+    fn positions_of<'a>(&'a self, name: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        let mask = self.table.len() - 1;
+        let mut slot = self.hasher.hash_one(name) as usize & mask;
+        std::iter::from_fn(move || {
+            let index = self.table[slot].checked_sub(1)? as usize;
+            slot = (slot + 1) & mask;
+            Some(index)
+        })
+        .filter(move |&index| self.name(index) == name)
+    }
+
+    pub(crate) fn values(&self) -> impl ExactSizeIterator<Item = Cow<'_, ZipEntry>> {
+        (0..self.len()).map(|index| {
+            self.get_index(index)
+                .expect("index is within the number of entries")
+        })
+    }
+
+    fn name(&self, index: usize) -> &[u8] {
+        self.storage.name(index)
+    }
+
+    fn place_last(&mut self) {
+        if self.len() * 2 > self.table.len() {
+            self.table = vec![0; self.table.len() * 2];
+            for index in 0..self.len() - 1 {
+                self.place(index);
+            }
+        }
+        self.place(self.len() - 1);
+    }
+
+    fn place(&mut self, index: usize) {
+        let mask = self.table.len() - 1;
+        let mut slot = self.hasher.hash_one(self.name(index)) as usize & mask;
+        while self.table[slot] != 0 {
+            slot = (slot + 1) & mask;
+        }
+        self.table[slot] = index as u32 + 1;
+    }
+}
+
+impl FromIterator<ZipEntry> for ZipIndex {
+    fn from_iter<T: IntoIterator<Item = ZipEntry>>(iter: T) -> Self {
+        let entries: Vec<ZipEntry> = iter.into_iter().collect();
+        let mut index = Self::new(entries.len());
+        index.storage = entries;
+        for position in 0..index.len() {
+            index.place(position);
+        }
+        index
+    }
+}
+
+impl sealed::Storage for Vec<ZipEntry> {
+    fn with_capacity(capacity: usize) -> Self {
+        Vec::with_capacity(capacity)
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn push(&mut self, value: &[u8]) -> ZipResult<()> {
+        self.push(ZipEntry::try_from(value)?);
+        Ok(())
+    }
+
+    fn get(&self, index: usize) -> Option<Cow<'_, ZipEntry>> {
+        self.as_slice().get(index).map(Cow::Borrowed)
+    }
+
+    fn name(&self, index: usize) -> &[u8] {
+        self[index].file_name.raw_name()
+    }
+}
+
+impl sealed::Storage for CompactStorage {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            records: Vec::with_capacity(capacity),
+            strings: Vec::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Only checks what decoding the entry later could fail on up front: the
+    /// record fits in `value` and its host system is known.
+    fn push(&mut self, value: &[u8]) -> ZipResult<()> {
+        let truncated = || ZipError::InvalidArchive("Truncated Central Directory Header".into());
+        let mut header: [u8; HEADER_SIZE] =
+            value.get(..HEADER_SIZE).ok_or_else(truncated)?.try_into()?;
+        let length = |at: usize| u16::from_le_bytes([header[at], header[at + 1]]) as usize;
+        let (name_length, extra_field_length, comment_length) =
+            (length(28), length(30), length(32));
+        let start_idx = HEADER_SIZE + name_length + extra_field_length;
+        if value.len() < start_idx + comment_length {
+            Err(truncated())?
+        }
+        AttributeCompatibility::try_from(header[5])?;
+        let name = &value[HEADER_SIZE..HEADER_SIZE + name_length];
+        let zip64 = zip64_block(&value[HEADER_SIZE + name_length..start_idx]).unwrap_or_default();
+        let comment = &value[start_idx..start_idx + comment_length];
+
+        header[30..32].copy_from_slice(&(zip64.len() as u16).to_le_bytes());
+        self.records.push(Record {
+            header,
+            offset: self.strings.len() as u64,
+        });
+        self.strings.extend_from_slice(name);
+        self.strings.extend_from_slice(zip64);
+        self.strings.extend_from_slice(comment);
+        Ok(())
+    }
+
+    fn get(&self, index: usize) -> Option<Cow<'_, ZipEntry>> {
+        let record = self.records.get(index)?;
+        let (name, extra, comment) = self.strings(record);
+        let entry = ZipEntry::from_parts(&record.header, name, extra, comment).ok()?;
+        Some(Cow::Owned(entry))
+    }
+
+    fn name(&self, index: usize) -> &[u8] {
+        self.strings(&self.records[index]).0
+    }
+}
+
+impl CompactStorage {
+    fn strings(&self, record: &Record) -> (&[u8], &[u8], &[u8]) {
+        let length = |at: usize| u16::from_le_bytes([record.header[at], record.header[at + 1]]);
+        let name_end = record.offset as usize + length(28) as usize;
+        let extra_end = name_end + length(30) as usize;
+        (
+            &self.strings[record.offset as usize..name_end],
+            &self.strings[name_end..extra_end],
+            &self.strings[extra_end..extra_end + length(32) as usize],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{CompactStorage, DuplicatePolicy, Record, Storage, ZipIndex},
+        crate::specs::ZipEntry,
+        crate::{compact::CompactZipArchive, testing::build_archive, verify::Problem, ZipArchive},
+        futures_lite::{future, io::Cursor},
+        std::{borrow::Cow, ffi::OsStr},
+    };
+
+    #[test]
+    fn compact_index_matches_full_index() {
+        future::block_on(async {
+            let names: Vec<String> = (0..100)
+                .map(|idx| format!("dir{}/{idx}.txt", idx % 7))
+                .collect();
+            let mut files: Vec<(&str, &[u8])> = names
+                .iter()
+                .map(|name| (name.as_str(), name.as_bytes()))
+                .collect();
+            files.push(("dir0/", b""));
+            let archive = build_archive(&files);
+
+            let full = ZipArchive::new(Cursor::new(archive.clone())).await.unwrap();
+            let mut compact = CompactZipArchive::new(Cursor::new(archive)).await.unwrap();
+            assert!(compact.entries().eq(full.entries().map(Cow::Borrowed)));
+            assert_eq!(
+                compact.entry_by_name("dir3/10.txt").as_deref(),
+                full.entry_by_name("dir3/10.txt")
+            );
+            assert!(compact.entry_by_name("dir3/missing.txt").is_none());
+            assert!(compact.entry_by_name("dir0/").unwrap().is_dir());
+
+            let file = compact.file_by_name("dir5/12.txt").await.unwrap();
+            assert_eq!(&*file, b"dir5/12.txt");
+        })
+    }
+
+    #[test]
+    fn keep_records_with_the_same_name() {
+        let archive = build_archive(&[("a.txt", b"one"), ("b.txt", b"two")]);
+        let start = archive
+            .windows(4)
+            .position(|window| window == 0x02014b50u32.to_le_bytes())
+            .unwrap();
+
+        fn check<S: Storage>(header: &[u8]) {
+            let mut index = ZipIndex::<S>::new(1);
+            for _ in 0..20 {
+                index.push_header(header).unwrap();
+            }
+            assert_eq!(index.len(), 20);
+            assert!(index.positions(OsStr::new("a.txt")).eq(0..20));
+            assert_eq!(index.positions(OsStr::new("b.txt")).next(), None);
+            assert_eq!(index.duplicates(), [(0..20).collect::<Vec<_>>()]);
+            assert_eq!(
+                index.get_index(19).unwrap().crc32(),
+                crc32fast::hash(b"one")
+            );
+        }
+        check::<Vec<ZipEntry>>(&archive[start..]);
+        check::<CompactStorage>(&archive[start..]);
+        assert!(ZipIndex::<CompactStorage>::new(1)
+            .push_header(&archive[start..start + 40])
+            .is_err());
+        assert!(std::mem::size_of::<Record>() <= 56);
+    }
+
+    #[test]
+    fn read_sizes_from_zip64_block() {
+        let name = b"big.bin";
+        let mut extra = Vec::new();
+        extra.extend_from_slice(&0x5455u16.to_le_bytes());
+        extra.extend_from_slice(&5u16.to_le_bytes());
+        extra.extend_from_slice(&[1, 0, 0, 0, 0]);
+        extra.extend_from_slice(&0x0001u16.to_le_bytes());
+        extra.extend_from_slice(&24u16.to_le_bytes());
+        for value in [5u64 << 32, 3 << 32, 7 << 32] {
+            extra.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        header.extend_from_slice(&[45, 3, 45, 0, 0, 0, 0, 0, 0, 0, 0x21, 0]);
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&[0xff; 8]);
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+        header.extend_from_slice(&[0; 6]);
+        header.extend_from_slice(&(0o100644u32 << 16).to_le_bytes());
+        header.extend_from_slice(&[0xff; 4]);
+        header.extend_from_slice(name);
+        header.extend_from_slice(&extra);
+
+        fn check<S: Storage>(header: &[u8]) {
+            let mut index = ZipIndex::<S>::new(1);
+            index.push_header(header).unwrap();
+            let entry = index.get_index(0).unwrap();
+            assert_eq!(entry.uncompressed_size(), 5 << 32);
+            assert_eq!(entry.compressed_size(), 3 << 32);
+            assert_eq!(entry.header_offset(), 7 << 32);
+        }
+        check::<Vec<ZipEntry>>(&header);
+        check::<CompactStorage>(&header);
+    }
+
+    #[test]
+    fn keep_duplicate_entry_names() {
+        future::block_on(async {
+            let files: [(&str, &[u8]); 3] = [
+                ("a.txt", b"first"),
+                ("b.txt", b"bravo"),
+                ("a.txt", b"second"),
+            ];
+            let archive = build_archive(&files);
+
+            let mut zip = ZipArchive::new(Cursor::new(archive.clone())).await.unwrap();
+            assert_eq!(zip.len(), 3);
+            assert_eq!(zip.indices_of("a.txt"), [0, 2]);
+            let duplicates = zip.duplicates();
+            assert_eq!(duplicates.len(), 1);
+            assert_eq!(duplicates[0].0.as_os_str(), "a.txt");
+            assert_eq!(duplicates[0].1, [0, 2]);
+
+            assert_eq!(zip.duplicate_policy(), DuplicatePolicy::Last);
+            assert_eq!(&*zip.file_by_name("a.txt").await.unwrap(), b"second");
+            zip.set_duplicate_policy(DuplicatePolicy::First);
+            assert_eq!(&*zip.file_by_name("a.txt").await.unwrap(), b"first");
+            assert_eq!(
+                zip.entry_by_name("a.txt").unwrap().crc32(),
+                crc32fast::hash(b"first")
+            );
+
+            let report = zip.verify().await;
+            assert!(matches!(
+                report
+                    .issues()
+                    .iter()
+                    .map(|issue| (issue.index(), issue.problem()))
+                    .collect::<Vec<_>>()[..],
+                [(2, Problem::DuplicateName { index: 0 })]
+            ));
+
+            let mut compact = CompactZipArchive::new(Cursor::new(archive)).await.unwrap();
+            assert_eq!(compact.indices_of("a.txt"), [0, 2]);
+            assert_eq!(compact.duplicates(), duplicates);
+            assert_eq!(&*compact.file_by_name("a.txt").await.unwrap(), b"second");
+            compact.set_duplicate_policy(DuplicatePolicy::First);
+            assert_eq!(
+                compact.entry_by_name("a.txt").unwrap().crc32(),
+                crc32fast::hash(b"first")
+            );
+            assert_eq!(compact.verify().await.issues().len(), 1);
+        })
+    }
+}
@@ -1,32 +1,66 @@
 #![warn(dead_code)]
 
-pub(crate) mod datetime;
+pub mod compact;
+#[cfg(feature = "tokio")]
+pub mod compat;
+pub mod datetime;
+pub mod diff;
 pub mod error;
+pub mod glob;
+pub mod index;
+pub mod lookup;
 pub mod path;
 pub mod read;
+pub mod recover;
+pub mod shared;
 pub mod specs;
+#[cfg(test)]
+mod testing;
+pub mod tree;
+pub mod verify;
+pub mod write;
 
 pub use specs::compression;
 use {
-    async_compression::futures::bufread::*,
     datetime::ZipDateTime,
     error::{ZipError, ZipResult},
-    indexmap::IndexMap,
-    path::ZipPath,
-    read::ZipAsyncReadExt,
-    smol::{io::{AsyncRead, AsyncSeek, AsyncSeekExt, SeekFrom}, stream::Stream},
+    futures_lite::{
+        io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, SeekFrom},
+        stream::Stream,
+    },
+    glob::{CaseSensitivity, Pattern},
+    index::{DuplicatePolicy, Storage, ZipIndex},
+    lookup::Lookup,
+    path::{RewrittenName, ZipPath},
+    read::{
+        nested::DEFAULT_MAX_DEPTH,
+        sequential::{ReadAhead, DEFAULT_READ_AHEAD},
+        volume::{MultiVolumeReader, Volumes},
+        ZipAsyncReadExt,
+    },
     specs::{
-        compression::{Compression, Decode},
-        extra_field::ExtraField,
-        GeneralPurposeFlag, ZipEntry,
+        compression::Compression, extra_field::ExtraField, GeneralPurposeFlag,
+        ZipCentralDirectoryEndRecord, ZipEntry,
+    },
+    std::{
+        ffi::OsStr,
+        ops::Deref,
+        path::{Path, PathBuf},
     },
-    std::{ffi::OsStr, ops::Deref, pin::Pin},
 };
 
-pub struct ZipArchive<R> {
+pub struct ZipArchive<R, I = Vec<ZipEntry>> {
     comment: Option<String>,
-    pub(crate) entries: IndexMap<ZipPath, ZipEntry>,
+    pub(crate) entries: ZipIndex<I>,
     pub(crate) reader: R,
+    pub(crate) volumes: Volumes,
+    pub(crate) central_directory_offset: u64,
+    prefix_len: u64,
+    depth: usize,
+    max_depth: usize,
+    duplicates: DuplicatePolicy,
+    lookup: Lookup,
+    read_ahead: u64,
 }
 
 #[derive(Debug)]
@@ -36,8 +70,8 @@ pub struct ZipFile {
     pub compression: Compression,
     pub last_mod_datetime: ZipDateTime,
     pub crc32: u32,
-    pub compressed_size: u32,
-    pub uncompressed_size: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
     pub file_name: ZipPath,
     pub extra_field: Option<Vec<ExtraField>>,
     pub(crate) data: Vec<u8>,
@@ -51,19 +85,215 @@ impl Deref for ZipFile {
     }
 }
 
+impl<R, I> ZipArchive<R, I>
+where
+    I: Storage,
+{
+    pub fn file_names(&self) -> Vec<ZipPath> {
+        self.entries
+            .values()
+            .map(|entry| entry.file_name.clone())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn comment(&self) -> &Option<String> {
+        &self.comment
+    }
+
+    pub fn volume_count(&self) -> usize {
+        self.volumes.len()
+    }
+
+    pub fn prefix_len(&self) -> u64 {
+        self.prefix_len
+    }
+
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.duplicates
+    }
+
+    /// Chooses which record lookups by name return when several share a name.
+    pub fn set_duplicate_policy(&mut self, policy: DuplicatePolicy) {
+        self.duplicates = policy;
+    }
+
+    pub fn read_ahead(&self) -> u64 {
+        self.read_ahead
+    }
+
+    /// Sets how many bytes `stream` reads ahead of the current entry. With 0,
+    /// the reader seeks to every entry instead.
+    pub fn set_read_ahead(&mut self, size: u64) {
+        self.read_ahead = size;
+    }
+
+    /// Indices of every entry named `path`, in central directory order.
+    pub fn indices_of<S>(&self, path: S) -> Vec<usize>
+    where
+        S: AsRef<OsStr>,
+    {
+        self.entries.positions(path.as_ref()).collect()
+    }
+
+    /// Names that appear more than once, with the indices of their entries.
+    pub fn duplicates(&self) -> Vec<(ZipPath, Vec<usize>)> {
+        self.entries
+            .duplicates()
+            .into_iter()
+            .filter_map(|indices| {
+                let name = self.entries.get_index(indices[0])?.file_name.clone();
+                Some((name, indices))
+            })
+            .collect()
+    }
+
+    /// Entries whose stored name is not safe to extract as is, with the path
+    /// each one is extracted to instead and the reasons it was changed.
+    pub fn rewritten_names(&self) -> Vec<RewrittenName> {
+        self.entries
+            .values()
+            .enumerate()
+            .filter_map(|(index, entry)| RewrittenName::new(index, &entry.file_name))
+            .collect()
+    }
+
+    pub(crate) fn index_of(&self, path: &OsStr) -> Option<usize> {
+        self.duplicates
+            .pick(self.entries.positions(path))
+            .or_else(|| self.duplicates.pick(self.lookup_positions(path)))
+    }
+}
+
+impl<R> ZipArchive<R> {
+    pub fn entries(&self) -> impl ExactSizeIterator<Item = &ZipEntry> {
+        self.entries.as_slice().iter()
+    }
+
+    pub fn entry_by_name<S>(&self, path: S) -> Option<&ZipEntry>
+    where
+        S: AsRef<OsStr>,
+    {
+        self.entries.as_slice().get(self.index_of(path.as_ref())?)
+    }
+
+    pub fn entry_by_index(&self, index: usize) -> Option<&ZipEntry> {
+        self.entries.as_slice().get(index)
+    }
+
+    pub fn glob(
+        &self,
+        pattern: &str,
+        case: CaseSensitivity,
+    ) -> ZipResult<impl Iterator<Item = &ZipEntry>> {
+        let pattern = Pattern::with_case(pattern, case)?;
+        Ok(self.entries_matching(pattern))
+    }
+
+    pub fn entries_matching(&self, pattern: Pattern) -> impl Iterator<Item = &ZipEntry> {
+        self.entries()
+            .filter(move |entry| pattern.matches(entry.name().as_os_str()))
+    }
+
+    pub fn entries_with_prefix(
+        &self,
+        prefix: &str,
+        case: CaseSensitivity,
+    ) -> impl Iterator<Item = &ZipEntry> {
+        let prefix = case.fold(prefix);
+        self.entries().filter(move |entry| {
+            case.fold(&entry.name().to_string_lossy())
+                .starts_with(&prefix)
+        })
+    }
+
+    pub fn entries_in<P>(
+        &self,
+        directory: P,
+        case: CaseSensitivity,
+    ) -> impl Iterator<Item = &ZipEntry>
+    where
+        P: AsRef<Path>,
+    {
+        let directory = case.fold(&directory.as_ref().to_string_lossy());
+        self.entries().filter(move |entry| {
+            let name = case.fold(&entry.name().to_string_lossy());
+            let name = Path::new(&name);
+            name.starts_with(&directory) && name != Path::new(&directory)
+        })
+    }
+}
+
 impl<R> ZipArchive<R>
 where
     R: AsyncRead + AsyncSeek + Unpin,
 {
-    pub async fn new(mut reader: R) -> ZipResult<Self> {
-        let eocdr = reader.read_zip_cd_end().await?;
-        let entries = reader.read_zip_entry(&eocdr).await?;
+    pub async fn new(reader: R) -> ZipResult<Self> {
+        Self::open(reader).await
+    }
+}
+
+impl<R, I> ZipArchive<R, I>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    I: Storage,
+{
+    pub(crate) async fn open(mut reader: R) -> ZipResult<Self> {
+        let eocdr = reader.read_zip_cd_end(&Volumes::default()).await?;
+        if eocdr.disk_number != 0 {
+            Err(ZipError::InvalidArchive(
+                format!(
+                    "Archive spans {} volumes, open it with ZipArchive::with_volumes",
+                    eocdr.disk_number + 1
+                )
+                .into(),
+            ))?
+        }
+
+        Self::with_layout(reader, eocdr, Volumes::default()).await
+    }
+
+    async fn with_layout(
+        mut reader: R,
+        eocdr: ZipCentralDirectoryEndRecord,
+        mut volumes: Volumes,
+    ) -> ZipResult<Self> {
+        let expected = volumes.resolve(
+            eocdr.central_directory_start_disk,
+            eocdr.central_directory_offset,
+        )?;
+        let actual = eocdr.offset.saturating_sub(eocdr.central_directory_size);
+        if eocdr.central_directory_start_disk == 0 {
+            volumes.correct(actual.saturating_sub(expected));
+        }
+
+        let entries = reader.read_zip_entry(&eocdr, &volumes).await?;
+        let prefix_len = entries
+            .values()
+            .filter(|entry| entry.disk_start == 0)
+            .map(|entry| volumes.resolve(0, entry.file_header_offset))
+            .try_fold(actual, |min, offset| offset.map(|offset| min.min(offset)))?;
 
         let comment = eocdr.comment;
         Ok(Self {
             reader,
             entries,
             comment,
+            volumes,
+            central_directory_offset: actual,
+            prefix_len,
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            duplicates: DuplicatePolicy::default(),
+            lookup: Lookup::default(),
+            read_ahead: DEFAULT_READ_AHEAD,
         })
     }
 
@@ -71,56 +301,99 @@ where
     where
         S: AsRef<OsStr>,
     {
-        let key = ZipPath::from(path.as_ref());
-        let entry = match self.entries.get(&key) {
-            Some(value) => value,
+        let entry = match self.index_of(path.as_ref()) {
+            Some(index) => self.entries.get_index(index).expect("index of an entry"),
             None => Err(ZipError::InvalidArchive("Invalid Key".into()))?,
+        }
+        .into_owned();
+        self.read_file(&entry).await
+    }
+
+    pub async fn file_by_index(&mut self, index: usize) -> ZipResult<ZipFile> {
+        let entry = match self.entries.get_index(index) {
+            Some(value) => value.into_owned(),
+            None => Err(ZipError::InvalidArchive("Invalid Index".into()))?,
         };
+        self.read_file(&entry).await
+    }
 
-        let offset = entry.file_header_offset as u64;
+    /// Reads the payload with the size from the central directory, which a
+    /// local header followed by a data descriptor leaves at zero.
+    async fn read_file(&mut self, entry: &ZipEntry) -> ZipResult<ZipFile> {
+        let offset = self
+            .volumes
+            .resolve(entry.disk_start as u32, entry.file_header_offset)?;
         self.reader.seek(SeekFrom::Start(offset)).await?;
-        let mut file = self.reader.read_zipfile().await?;
+        let mut file = self.reader.read_zipfile(entry).await?;
         file.file_name.metadata = entry.file_name.metadata.clone();
         Ok(file)
     }
 
-    pub async fn file_by_index(&mut self, index: usize) -> ZipResult<ZipFile> {
+    pub(crate) async fn read_raw(&mut self, index: usize) -> ZipResult<(ZipEntry, Vec<u8>)> {
         let entry = match self.entries.get_index(index) {
-            Some((_name, value)) => value,
+            Some(value) => value.into_owned(),
             None => Err(ZipError::InvalidArchive("Invalid Index".into()))?,
         };
-        let offset = entry.file_header_offset as u64;
+        let offset = self
+            .volumes
+            .resolve(entry.disk_start as u32, entry.file_header_offset)?;
         self.reader.seek(SeekFrom::Start(offset)).await?;
-        let mut file = self.reader.read_zipfile().await?;
-        file.file_name.metadata = entry.file_name.metadata.clone();
-        Ok(file)
-    }
+        self.reader.read_local_header().await?;
 
-    pub fn file_names(&self) -> Vec<ZipPath> {
-        self.entries
-            .iter()
-            .map(|(name, _value)| name.clone())
-            .collect()
+        let mut data = Vec::with_capacity(entry.compressed_size as usize);
+        (&mut self.reader)
+            .take(entry.compressed_size)
+            .read_to_end(&mut data)
+            .await?;
+        Ok((entry, data))
     }
 
-    pub fn len(&self) -> usize {
-        self.entries.len()
+    pub async fn prefix(&mut self) -> ZipResult<Vec<u8>> {
+        let mut buffer = Vec::with_capacity(self.prefix_len as usize);
+        self.reader.seek(SeekFrom::Start(0)).await?;
+        (&mut self.reader)
+            .take(self.prefix_len)
+            .read_to_end(&mut buffer)
+            .await?;
+        Ok(buffer)
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.entries.is_empty()
+    pub fn stream(&mut self) -> impl Stream<Item = ZipResult<ZipFile>> + Unpin + '_ {
+        self.stream_filter(|_entry| true)
     }
 
-    pub fn comment(&self) -> &Option<String> {
-        &self.comment
+    pub fn stream_matching(
+        &mut self,
+        pattern: Pattern,
+    ) -> impl Stream<Item = ZipResult<ZipFile>> + Unpin + '_ {
+        self.stream_filter(move |entry| pattern.matches(entry.name().as_os_str()))
     }
 
-    pub fn stream(&mut self) -> Pin<Box<dyn Stream<Item = ZipResult<ZipFile>> + '_>> {
+    /// Entries are read in the order they are stored in the archive, which can
+    /// differ from the central directory order.
+    pub fn stream_filter<'a, F>(
+        &'a mut self,
+        mut filter: F,
+    ) -> impl Stream<Item = ZipResult<ZipFile>> + Unpin + 'a
+    where
+        F: FnMut(&ZipEntry) -> bool + 'a,
+    {
         Box::pin(async_fn_stream::try_fn_stream(|emitter| async move {
-            for (_, entry) in &self.entries {
-                let offset = entry.file_header_offset as u64;
-                self.reader.seek(SeekFrom::Start(offset)).await?;
-                let mut file = self.reader.read_zipfile().await?;
+            let mut entries = Vec::new();
+            for (index, entry) in self.entries.values().enumerate() {
+                if filter(&entry) {
+                    let offset = self
+                        .volumes
+                        .resolve(entry.disk_start as u32, entry.file_header_offset)?;
+                    entries.push((offset, index));
+                }
+            }
+            entries.sort_by_key(|(offset, _index)| *offset);
+
+            let mut reader = ReadAhead::new(&mut self.reader, self.read_ahead);
+            for (offset, index) in entries {
+                let entry = self.entries.get_index(index).expect("index of an entry");
+                let mut file = reader.read_zipfile(offset, &entry).await?;
                 file.file_name.metadata = entry.file_name.metadata.clone();
                 let _ = emitter.emit(file).await;
             }
@@ -129,31 +402,55 @@ where
     }
 }
 
+impl<R> ZipArchive<MultiVolumeReader<R>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    pub async fn with_volumes(volumes: Vec<R>) -> ZipResult<Self> {
+        let mut reader = MultiVolumeReader::new(volumes).await?;
+        let starts = Volumes::new(reader.volume_starts());
+        let eocdr = reader.read_zip_cd_end(&starts).await?;
+        let disks = eocdr.disk_number as usize + 1;
+
+        let volumes = if disks == 1 {
+            Volumes::default()
+        } else if disks == reader.volume_count() {
+            starts
+        } else {
+            Err(ZipError::InvalidArchive(
+                format!(
+                    "Archive spans {} volumes but {} were given",
+                    disks,
+                    reader.volume_count()
+                )
+                .into(),
+            ))?
+        };
+
+        Self::with_layout(reader, eocdr, volumes).await
+    }
+}
+
 impl ZipFile {
     pub async fn extract(self) -> ZipResult<Vec<u8>> {
         match self.compression {
             Compression::Stored => Ok(self.data),
-            Compression::Deflate => {
-                DeflateDecoder::decode(&*self.data, self.uncompressed_size as usize).await
-            }
-            Compression::Deflate64 => {
-                Deflate64Decoder::decode(&*self.data, self.uncompressed_size as usize).await
-            }
-            Compression::Bzip2 => {
-                BzDecoder::decode(&*self.data, self.uncompressed_size as usize).await
-            }
-            Compression::Lzma => {
-                LzmaDecoder::decode(&*self.data, self.uncompressed_size as usize).await
-            }
-            Compression::Zstd => {
-                ZstdDecoder::decode(&*self.data, self.uncompressed_size as usize).await
-            }
-            Compression::Xz => {
-                XzDecoder::decode(&*self.data, self.uncompressed_size as usize).await
+            compression => {
+                compression
+                    .decompress(
+                        &self.data,
+                        self.uncompressed_size as usize,
+                        self.flags.compression_options,
+                    )
+                    .await
             }
         }
     }
 
+    pub fn version_needed(&self) -> u16 {
+        self.version_needed
+    }
+
     pub fn is_dir(&self) -> bool {
         self.file_name.is_dir()
     }
@@ -161,6 +458,16 @@ impl ZipFile {
     pub fn is_file(&self) -> bool {
         self.file_name.is_file()
     }
+
+    /// The name exactly as stored in the local file header.
+    pub fn raw_name(&self) -> &[u8] {
+        self.file_name.raw_name()
+    }
+
+    /// Where this file is extracted to, relative to the destination.
+    pub fn sanitized_path(&self) -> PathBuf {
+        self.file_name.sanitized().0
+    }
 }
 
 #[cfg(test)]
@@ -221,7 +528,7 @@ mod tests {
                 let mut zip = zip.stream();
                 while let Some(zip_file) = zip.next().await {
                     let zip_file = zip_file.unwrap();
-                    let path = Path::new(test_path).join(&*zip_file.file_name);
+                    let path = Path::new(test_path).join(zip_file.sanitized_path());
                     dbg!(&path);
                     let parent = path.parent().unwrap();
                     if zip_file.is_file() {
@@ -0,0 +1,260 @@
+use {
+    crate::{
+        index::{Storage, ZipIndex},
+        ZipArchive,
+    },
+    std::{
+        collections::HashMap,
+        ffi::OsStr,
+        hash::{BuildHasher, Hash, RandomState},
+    },
+};
+#[cfg(feature = "unicode")]
+use {caseless::Caseless, unicode_normalization::UnicodeNormalization};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum CaseFolding {
+    #[default]
+    Exact,
+    /// Only `A` to `Z` match their lowercase letters.
+    Ascii,
+    /// Full Unicode case folding, so that `STRASSE` matches `straße`.
+    #[cfg(feature = "unicode")]
+    Unicode,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Normalization {
+    #[default]
+    None,
+    /// Compares names in composed form.
+    #[cfg(feature = "unicode")]
+    Nfc,
+    /// Compares names in decomposed form, as stored by macOS.
+    #[cfg(feature = "unicode")]
+    Nfd,
+}
+
+/// How names are compared when a lookup by name finds no exact match.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct LookupMode {
+    case: CaseFolding,
+    normalization: Normalization,
+}
+
+/// Entry indices by the hash of their key under `mode`. Keys are not kept, so
+/// the map costs a slot and an index per entry rather than a copy of every
+/// name; candidates are compared again on lookup.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Lookup {
+    mode: LookupMode,
+    keys: HashMap<u64, Vec<usize>>,
+    hasher: RandomState,
+}
+
+impl LookupMode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn case_folding(mut self, case: CaseFolding) -> Self {
+        self.case = case;
+        self
+    }
+
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    pub fn is_exact(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The form of `name` that is compared under this mode.
+    pub fn key(&self, name: &str) -> String {
+        let name = self.normalize(name);
+        match self.case {
+            CaseFolding::Exact => name,
+            CaseFolding::Ascii => name.to_ascii_lowercase(),
+            // Folding can produce unnormalized text, so normalize again.
+            #[cfg(feature = "unicode")]
+            CaseFolding::Unicode => {
+                self.normalize(&name.chars().default_case_fold().collect::<String>())
+            }
+        }
+    }
+
+    fn normalize(&self, name: &str) -> String {
+        match self.normalization {
+            Normalization::None => name.to_owned(),
+            #[cfg(feature = "unicode")]
+            Normalization::Nfc => name.nfc().collect(),
+            #[cfg(feature = "unicode")]
+            Normalization::Nfd => name.nfd().collect(),
+        }
+    }
+
+    fn group<K, S, F>(&self, entries: &ZipIndex<S>, hash: F) -> HashMap<K, Vec<usize>>
+    where
+        K: Eq + Hash,
+        S: Storage,
+        F: Fn(String) -> K,
+    {
+        let mut keys: HashMap<K, Vec<usize>> = HashMap::new();
+        for (index, entry) in entries.values().enumerate() {
+            if let Some(name) = entry.file_name.to_str() {
+                keys.entry(hash(self.key(name))).or_default().push(index);
+            }
+        }
+        keys
+    }
+}
+
+impl<R, I> ZipArchive<R, I>
+where
+    I: Storage,
+{
+    pub fn lookup_mode(&self) -> LookupMode {
+        self.lookup.mode
+    }
+
+    /// Lets lookups by name that find no exact match fall back to the entries
+    /// whose name is equal under `mode`.
+    pub fn set_lookup_mode(&mut self, mode: LookupMode) {
+        let hasher = RandomState::new();
+        self.lookup = Lookup {
+            mode,
+            keys: match mode.is_exact() {
+                true => HashMap::new(),
+                false => mode.group(&self.entries, |key| hasher.hash_one(key)),
+            },
+            hasher,
+        };
+    }
+
+    /// Groups of entries with different names that are equal under `mode`, in
+    /// central directory order. On a filesystem comparing names that way, each
+    /// group would extract to the same file.
+    pub fn collisions(&self, mode: LookupMode) -> Vec<Vec<usize>> {
+        let name = |index: usize| {
+            self.entries
+                .get_index(index)
+                .map(|entry| entry.file_name.clone())
+        };
+        let mut groups: Vec<Vec<usize>> = mode
+            .group(&self.entries, |key| key)
+            .into_values()
+            .filter(|indices| indices.iter().any(|&index| name(index) != name(indices[0])))
+            .collect();
+        groups.sort_unstable();
+        groups
+    }
+
+    pub(crate) fn lookup_positions(&self, path: &OsStr) -> impl Iterator<Item = usize> + '_ {
+        let mode = self.lookup.mode;
+        let key = path
+            .to_str()
+            .filter(|_name| !mode.is_exact())
+            .map(|name| mode.key(name));
+        let candidates = key
+            .as_ref()
+            .and_then(|key| self.lookup.keys.get(&self.lookup.hasher.hash_one(key)))
+            .map_or(&[][..], Vec::as_slice);
+        candidates.iter().copied().filter(move |&index| {
+            let entry = self.entries.get_index(index);
+            entry.and_then(|entry| entry.file_name.to_str().map(|name| mode.key(name))) == key
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CaseFolding, LookupMode};
+    #[cfg(feature = "unicode")]
+    use {
+        super::Normalization,
+        crate::{index::DuplicatePolicy, testing::build_archive, ZipArchive},
+        smol::io::Cursor,
+    };
+
+    #[test]
+    fn fold_names_into_keys() {
+        let ascii = LookupMode::new().case_folding(CaseFolding::Ascii);
+        assert_eq!(ascii.key("Docs/README.md"), "docs/readme.md");
+        assert_ne!(ascii.key("STRASSE"), ascii.key("straße"));
+        assert!(LookupMode::new().is_exact());
+        assert_eq!(LookupMode::new().key("Caf\u{e9}"), "Caf\u{e9}");
+    }
+
+    #[test]
+    #[cfg(feature = "unicode")]
+    fn fold_unicode_names_into_keys() {
+        let unicode = LookupMode::new().case_folding(CaseFolding::Unicode);
+        assert_eq!(unicode.key("STRASSE"), unicode.key("straße"));
+        assert_ne!(unicode.key("e\u{301}"), unicode.key("\u{e9}"));
+
+        for normalization in [Normalization::Nfc, Normalization::Nfd] {
+            let mode = LookupMode::new().normalization(normalization);
+            assert_eq!(mode.key("cafe\u{301}"), mode.key("caf\u{e9}"));
+            assert_ne!(mode.key("Café"), mode.key("café"));
+
+            let both = mode.case_folding(CaseFolding::Unicode);
+            assert_eq!(both.key("CAFE\u{301}"), both.key("caf\u{e9}"));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "unicode")]
+    fn look_up_names_by_mode() {
+        smol::block_on(async {
+            let files: [(&str, &[u8]); 4] = [
+                ("Docs/README.md", b"readme"),
+                ("cafe\u{301}.txt", b"decomposed"),
+                ("caf\u{e9}.txt", b"composed"),
+                ("docs/readme.md", b"lower"),
+            ];
+            let mut zip = ZipArchive::new(Cursor::new(build_archive(&files)))
+                .await
+                .unwrap();
+            assert!(zip.entry_by_name("DOCS/readme.MD").is_none());
+            assert!(zip.entry_by_name("STRASSE").is_none());
+
+            zip.set_lookup_mode(LookupMode::new().case_folding(CaseFolding::Ascii));
+            assert_eq!(
+                &*zip.file_by_name("docs/readme.md").await.unwrap(),
+                b"lower"
+            );
+            assert_eq!(
+                &*zip.file_by_name("Docs/README.md").await.unwrap(),
+                b"readme"
+            );
+            assert_eq!(
+                &*zip.file_by_name("DOCS/readme.MD").await.unwrap(),
+                b"lower"
+            );
+            assert!(zip.entry_by_name("CAF\u{c9}.txt").is_none());
+
+            zip.set_lookup_mode(
+                LookupMode::new()
+                    .case_folding(CaseFolding::Unicode)
+                    .normalization(Normalization::Nfc),
+            );
+            assert_eq!(
+                &*zip.file_by_name("CAF\u{c9}.TXT").await.unwrap(),
+                b"composed"
+            );
+            zip.set_duplicate_policy(DuplicatePolicy::First);
+            assert_eq!(
+                &*zip.file_by_name("CAFE\u{301}.TXT").await.unwrap(),
+                b"decomposed"
+            );
+
+            assert_eq!(zip.collisions(LookupMode::new()), Vec::<Vec<usize>>::new());
+            let ascii = LookupMode::new().case_folding(CaseFolding::Ascii);
+            assert_eq!(zip.collisions(ascii), [[0, 3]]);
+            let nfd = LookupMode::new().normalization(Normalization::Nfd);
+            assert_eq!(zip.collisions(nfd), [[1, 2]]);
+        })
+    }
+}
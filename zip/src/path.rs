@@ -1,11 +1,12 @@
 use {
     crate::specs::attribute::Attributes,
     std::{
+        borrow::Cow,
         ffi::OsStr,
         fmt::Debug,
         hash::Hash,
         ops::Deref,
-        path::{Component, Path, PathBuf},
+        path::{Path, PathBuf},
     },
 };
 
@@ -13,6 +14,28 @@ pub(crate) trait Sanitize {
     fn sanitize(&mut self);
 }
 
+/// Why a name was changed to make it safe to extract.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Rewrite {
+    /// Started at the root, so it was made relative.
+    Absolute,
+    /// Contained `..` components, which were dropped.
+    Traversal,
+    /// Used `\` as a separator, which was replaced by `/`.
+    Backslash,
+    /// Started with a drive letter such as `C:`, which was dropped.
+    DriveLetter,
+}
+
+/// An entry whose name differs from the path it extracts to.
+#[derive(Clone, Debug)]
+pub struct RewrittenName {
+    index: usize,
+    name: ZipPath,
+    path: PathBuf,
+    rewrites: Vec<Rewrite>,
+}
+
 macro_rules! update {
     ($metadata:ident, $attribute:ident, $field:ident) => {
         if $metadata.$field != $attribute.$field {
@@ -29,6 +52,8 @@ macro_rules! update {
 #[derive(Clone)]
 pub struct ZipPath {
     inner: Box<OsStr>,
+    /// The name as stored when it is not UTF-8, such as a CP437 name.
+    raw: Option<Box<[u8]>>,
     pub metadata: Option<Attributes>,
 }
 
@@ -46,6 +71,7 @@ impl Default for ZipPath {
     fn default() -> Self {
         Self {
             inner: OsStr::new("").into(),
+            raw: None,
             metadata: None,
         }
     }
@@ -63,7 +89,7 @@ impl Eq for ZipPath {}
 
 impl Ord for ZipPath {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.inner.cmp(&other.inner)
+        self.raw_name().cmp(other.raw_name())
     }
 }
 
@@ -83,50 +109,66 @@ where
 
 impl Hash for ZipPath {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.inner.hash(state)
+        self.raw_name().hash(state)
     }
 }
 
 impl PartialEq for ZipPath {
     fn eq(&self, other: &Self) -> bool {
-        self.inner == other.inner
-    }
-
-    fn ne(&self, other: &Self) -> bool {
-        self.inner != other.inner
+        self.raw_name() == other.raw_name()
     }
 }
 
 impl PartialOrd for ZipPath {
-    fn ge(&self, other: &Self) -> bool {
-        self.inner >= other.inner
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
     }
+}
 
-    fn gt(&self, other: &Self) -> bool {
-        self.inner > other.inner
+impl Sanitize for ZipPath {
+    fn sanitize(&mut self) {
+        self.inner = self.sanitized().0.into_os_string().into();
+        self.raw = None;
     }
+}
 
-    fn le(&self, other: &Self) -> bool {
-        self.inner <= other.inner
+#[cfg(unix)]
+fn decode(bytes: &[u8]) -> Box<OsStr> {
+    use std::os::unix::ffi::OsStrExt;
+    OsStr::from_bytes(bytes).into()
+}
+
+#[cfg(not(unix))]
+fn decode(bytes: &[u8]) -> Box<OsStr> {
+    OsStr::new(&*String::from_utf8_lossy(bytes)).into()
+}
+
+impl RewrittenName {
+    pub(crate) fn new(index: usize, name: &ZipPath) -> Option<Self> {
+        let (path, rewrites) = name.sanitized();
+        (!rewrites.is_empty()).then(|| Self {
+            index,
+            name: name.clone(),
+            path,
+            rewrites,
+        })
     }
 
-    fn lt(&self, other: &Self) -> bool {
-        self.inner < other.inner
+    pub fn index(&self) -> usize {
+        self.index
     }
 
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.inner.partial_cmp(&other.inner)
+    /// The name as stored in the archive.
+    pub fn name(&self) -> &ZipPath {
+        &self.name
     }
-}
 
-impl Sanitize for ZipPath {
-    fn sanitize(&mut self) {
-        let path: PathBuf = Path::new(&self.inner)
-            .components()
-            .filter(|c| matches!(c, Component::Normal(_)))
-            .collect();
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
 
-        self.inner = path.as_os_str().into();
+    pub fn rewrites(&self) -> &[Rewrite] {
+        &self.rewrites
     }
 }
 
@@ -135,17 +177,78 @@ impl ZipPath {
         Self::default()
     }
 
+    /// A name read from an archive. Names that are not UTF-8 are kept byte for
+    /// byte and only decoded lossily where an `OsStr` cannot hold them.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(name) => Self::from(name),
+            Err(_error) => Self {
+                inner: decode(bytes),
+                raw: Some(bytes.into()),
+                ..Self::default()
+            },
+        }
+    }
+
     pub fn as_os_str(&self) -> &OsStr {
         OsStr::new(&*self.inner)
     }
 
+    /// The name exactly as stored in the archive.
+    pub fn raw_name(&self) -> &[u8] {
+        match &self.raw {
+            Some(raw) => raw,
+            None => self.inner.as_encoded_bytes(),
+        }
+    }
+
+    /// Relative path to extract this entry to, with every change made to the
+    /// stored name. `.` components and empty ones are dropped silently.
+    pub fn sanitized(&self) -> (PathBuf, Vec<Rewrite>) {
+        let mut rewrites = Vec::new();
+        let name = self.inner.to_string_lossy();
+        let name = match name.contains('\\') {
+            true => {
+                rewrites.push(Rewrite::Backslash);
+                Cow::Owned(name.replace('\\', "/"))
+            }
+            false => name,
+        };
+
+        let mut rest = &*name;
+        if let [drive, b':', ..] = rest.as_bytes() {
+            if drive.is_ascii_alphabetic() {
+                rewrites.push(Rewrite::DriveLetter);
+                rest = &rest[2..];
+            }
+        }
+        if rest.starts_with('/') {
+            rewrites.push(Rewrite::Absolute);
+        }
+
+        let mut path = PathBuf::new();
+        for component in rest.split('/') {
+            match component {
+                "" | "." => {}
+                ".." => {
+                    if !rewrites.contains(&Rewrite::Traversal) {
+                        rewrites.push(Rewrite::Traversal);
+                    }
+                }
+                component => path.push(component),
+            }
+        }
+        (path, rewrites)
+    }
+
     pub fn append<S>(&mut self, string: S)
     where
         S: AsRef<Path>,
     {
         let mut path = PathBuf::from(&self.inner);
         path.extend(string.as_ref());
-        self.inner = path.as_os_str().into()
+        self.inner = path.as_os_str().into();
+        self.raw = None;
     }
 
     pub fn is_dir(&self) -> bool {
@@ -173,7 +276,7 @@ impl ZipPath {
         if self.is_file() {
             let path = Path::new(&self.inner)
                 .components()
-                .last()
+                .next_back()
                 .map(|c| Path::new(c.as_os_str()));
             path
         } else {
@@ -199,3 +302,86 @@ impl ZipPath {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::Rewrite,
+        crate::{testing::build_archive, write::ZipWriter, ZipArchive},
+        smol::io::Cursor,
+        std::path::Path,
+    };
+
+    #[test]
+    fn report_rewritten_entry_names() {
+        smol::block_on(async {
+            let files: [(&str, &[u8]); 5] = [
+                ("docs/a.txt", b"alpha"),
+                ("/etc/passwd", b"root"),
+                ("../../up.txt", b"up"),
+                ("dir\\..\\b.txt", b"bravo"),
+                ("C:/c.txt", b"charlie"),
+            ];
+            let mut zip = ZipArchive::new(Cursor::new(build_archive(&files)))
+                .await
+                .unwrap();
+
+            let rewritten = zip.rewritten_names();
+            let reports: Vec<_> = rewritten
+                .iter()
+                .map(|name| (name.index(), name.path(), name.rewrites()))
+                .collect();
+            assert_eq!(
+                reports,
+                [
+                    (1, Path::new("etc/passwd"), &[Rewrite::Absolute][..]),
+                    (2, Path::new("up.txt"), &[Rewrite::Traversal]),
+                    (
+                        3,
+                        Path::new("dir/b.txt"),
+                        &[Rewrite::Backslash, Rewrite::Traversal]
+                    ),
+                    (
+                        4,
+                        Path::new("c.txt"),
+                        &[Rewrite::DriveLetter, Rewrite::Absolute]
+                    ),
+                ]
+            );
+
+            let entry = zip.entry_by_index(3).unwrap();
+            assert_eq!(entry.raw_name(), b"dir\\..\\b.txt");
+            assert_eq!(entry.sanitized_path(), Path::new("dir/b.txt"));
+            let file = zip.file_by_name("../../up.txt").await.unwrap();
+            assert_eq!(file.raw_name(), b"../../up.txt");
+            assert_eq!(file.sanitized_path(), Path::new("up.txt"));
+            assert!(zip.verify().await.is_ok());
+        })
+    }
+
+    #[test]
+    fn keep_names_that_are_not_utf8() {
+        smol::block_on(async {
+            let mut archive = build_archive(&[("caf?.txt", b"latte")]);
+            for idx in 0..archive.len() - 3 {
+                if &archive[idx..idx + 4] == b"caf?" {
+                    archive[idx + 3] = 0x82;
+                }
+            }
+            let mut zip = ZipArchive::new(Cursor::new(archive)).await.unwrap();
+            let entry = zip.entry_by_index(0).unwrap();
+            assert_eq!(entry.raw_name(), b"caf\x82.txt");
+            assert_eq!(entry.sanitized_path(), Path::new("caf\u{fffd}.txt"));
+            let file = zip.file_by_index(0).await.unwrap();
+            assert_eq!(file.raw_name(), b"caf\x82.txt");
+            assert_eq!(&*file, b"latte");
+            assert!(zip.verify().await.is_ok());
+
+            let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+            writer.copy_file(&mut zip, 0).await.unwrap();
+            let copy = writer.finish().await.unwrap().into_inner();
+            let copy = ZipArchive::new(Cursor::new(copy)).await.unwrap();
+            assert_eq!(copy.entry_by_index(0).unwrap().raw_name(), b"caf\x82.txt");
+        })
+    }
+}
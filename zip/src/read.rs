@@ -1,23 +1,26 @@
-pub(crate) mod poll;
+pub mod extract;
+pub mod nested;
+pub mod poll;
+pub(crate) mod sequential;
+pub mod volume;
 
 use {
     crate::{
-        datetime::ZipDateTime,
-        path::{Sanitize, ZipPath},
+        index::{Storage, ZipIndex},
+        path::ZipPath,
         specs::{
-            compression::Compression, extra_field::ExtraField, GeneralPurposeFlag,
+            extra_field::zip64_block, DataDescriptor, LocalFileHeader,
             Zip32CentralDirectoryEndRecord, Zip64CentralDirectoryEndLocator,
             Zip64CentralDirectoryEndRecord, ZipCentralDirectoryEndRecord, ZipEntry, ZipSpecs,
-            SIGNATURE_LENGTH,
+            DATA_DESCRIPTOR_LENGTH, DATA_DESCRIPTOR_SIGNATURE, SIGNATURE_LENGTH,
+            ZIP64_DATA_DESCRIPTOR_LENGTH,
         },
         ZipError, ZipFile, ZipResult,
     },
     fastsearch::FastSearch,
-    indexmap::IndexMap,
-    smol::{
-        io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, SeekFrom},
-        stream::{iter, StreamExt},
-    },
+    futures_lite::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, SeekFrom},
+    poll::ReadZipFile,
+    volume::Volumes,
 };
 
 pub(crate) trait ZipAsyncReadExt {
@@ -30,41 +33,13 @@ pub(crate) trait ZipAsyncReadExt {
         Ok(u32::from_le_bytes(buffer))
     }
 
-    async fn read_zip32_record(&mut self) -> ZipResult<(u64, Zip32CentralDirectoryEndRecord)>
-    where
-        Self: AsyncRead + AsyncSeek + Unpin,
-    {
-        let length = self.seek(SeekFrom::End(0)).await? as usize;
-        let signature = Zip32CentralDirectoryEndRecord::SIGNATURE.to_le_bytes();
-        let position = length.saturating_sub(Zip32CentralDirectoryEndRecord::MAX_SIZE);
-        let max = length - position;
-
-        let mut buffer = Vec::with_capacity(max);
-
-        self.seek(SeekFrom::Start(position as u64)).await?;
-        self.read_to_end(&mut buffer).await?;
-        if let Some(idx) = buffer.rsearch(&signature) {
-            let offset = (position + idx) as u64;
-            self.seek(SeekFrom::Start(offset + 4)).await?;
-            let mut buffer = [0; Zip32CentralDirectoryEndRecord::SIZE];
-            self.read_exact(&mut buffer).await?;
-            let record = Zip32CentralDirectoryEndRecord::try_from(buffer)?;
-            Ok((offset, record))
-        } else {
-            Err(ZipError::SignatureNotFound(
-                "Central Directory End Record Signature Not Found".into(),
-            ))
-        }
-    }
-
     async fn read_to_zip_path(&mut self, path: &mut ZipPath) -> ZipResult<usize>
     where
         Self: AsyncRead + Unpin,
     {
-        let mut string = String::new();
-        let read = self.read_to_string(&mut string).await?;
-        path.append(string);
-        path.sanitize();
+        let mut buffer = Vec::new();
+        let read = self.read_to_end(&mut buffer).await?;
+        *path = ZipPath::from_bytes(&buffer);
         Ok(read)
     }
 
@@ -84,6 +59,34 @@ pub(crate) trait ZipAsyncReadExt {
         }
     }
 
+    /// Reads the zip64 end record, which normally sits right before its
+    /// locator even when data was prepended and the stored offset is off.
+    /// Otherwise the locator's disk-relative offset is resolved through
+    /// `volumes`. Returns the position it was found at.
+    async fn read_zip64_record_before(
+        &mut self,
+        locator_position: u64,
+        locator: &Zip64CentralDirectoryEndLocator,
+        volumes: &Volumes,
+    ) -> ZipResult<(u64, Zip64CentralDirectoryEndRecord)>
+    where
+        Self: AsyncRead + AsyncSeek + Unpin,
+    {
+        let adjacent = locator_position.saturating_sub(
+            (SIGNATURE_LENGTH as usize + Zip64CentralDirectoryEndRecord::SIZE) as u64,
+        );
+        self.seek(SeekFrom::Start(adjacent)).await?;
+        if let Ok(record) = self.read_zip64_record().await {
+            return Ok((adjacent, record));
+        }
+        let stored = volumes.resolve(
+            locator.number_of_disk_with_zip64_central_directory_end,
+            locator.relative_offset,
+        )?;
+        self.seek(SeekFrom::Start(stored)).await?;
+        Ok((stored, self.read_zip64_record().await?))
+    }
+
     async fn read_zip64_record(&mut self) -> ZipResult<Zip64CentralDirectoryEndRecord>
     where
         Self: AsyncRead + AsyncSeek + Unpin,
@@ -100,7 +103,10 @@ pub(crate) trait ZipAsyncReadExt {
         }
     }
 
-    async fn read_zip_cd_end(&mut self) -> ZipResult<ZipCentralDirectoryEndRecord>
+    async fn read_zip_cd_end(
+        &mut self,
+        volumes: &Volumes,
+    ) -> ZipResult<ZipCentralDirectoryEndRecord>
     where
         Self: AsyncRead + AsyncSeek + Unpin,
     {
@@ -132,36 +138,25 @@ pub(crate) trait ZipAsyncReadExt {
                 || zip32_record.number_of_entries == u16::MAX
             {
                 let zip64 = Zip64CentralDirectoryEndRecord::SIGNATURE.to_le_bytes();
-                let zip64_record = if let Some(idx_64) = (&buffer[..idx_32]).rsearch(&zip64) {
-                    let idx = idx_64 + 4;
-                    Zip64CentralDirectoryEndRecord::try_from(TryInto::<[u8; 52]>::try_into(
-                        &buffer[idx..idx + 52],
-                    )?)?
-                } else {
-                    let locator = Zip64CentralDirectoryEndLocator::SIGNATURE.to_le_bytes();
-
-                    if let Some(lidx) = (&buffer[..idx_32]).rsearch(&locator) {
-                        self.seek(SeekFrom::Start((lidx + 4) as u64 + position))
-                            .await?;
-
-                        let mut buffer = [0; Zip64CentralDirectoryEndLocator::SIZE];
-                        self.read_exact(&mut buffer).await?;
-                        let locator = Zip64CentralDirectoryEndLocator::try_from(buffer)?;
-
-                        self.seek(SeekFrom::Start(locator.relative_offset + 4))
-                            .await?;
-                        let mut buffer = [0; Zip64CentralDirectoryEndRecord::SIZE];
-                        self.read_exact(&mut buffer).await?;
-                        Zip64CentralDirectoryEndRecord::try_from(buffer)?
+                let (offset, zip64_record) =
+                    if let Some(idx_64) = (&buffer[..idx_32]).rsearch(&zip64) {
+                        let idx = idx_64 + 4;
+                        let record = Zip64CentralDirectoryEndRecord::try_from(
+                            TryInto::<[u8; 52]>::try_into(&buffer[idx..idx + 52])?,
+                        )?;
+                        (position + idx_64 as u64, record)
                     } else {
-                        self.seek(SeekFrom::Start(position + (idx_32 - 20) as u64))
-                            .await?;
+                        let locator = Zip64CentralDirectoryEndLocator::SIGNATURE.to_le_bytes();
+                        let locator_position = match (&buffer[..idx_32]).rsearch(&locator) {
+                            Some(lidx) => position + lidx as u64,
+                            None => (position + idx_32 as u64).saturating_sub(20),
+                        };
+                        self.seek(SeekFrom::Start(locator_position)).await?;
                         let locator = self.read_zip64_locator().await?;
-                        self.seek(SeekFrom::Start(locator.relative_offset)).await?;
-                        self.read_zip64_record().await?
-                    }
-                };
-                let _version_made_by = Some(zip64_record.version_made_by);
+                        self.read_zip64_record_before(locator_position, &locator, volumes)
+                            .await?
+                    };
+                let version_made_by = Some(zip64_record.version_made_by);
                 let version_needed = Some(zip64_record.version_needed);
                 let disk_number = zip64_record.disk_number;
                 let central_directory_start_disk = zip64_record.central_directory_start_disk;
@@ -170,7 +165,7 @@ pub(crate) trait ZipAsyncReadExt {
                 let central_directory_size = zip64_record.central_directory_size;
                 let central_directory_offset = zip64_record.central_directory_offset;
                 Ok(ZipCentralDirectoryEndRecord {
-                    _version_made_by,
+                    version_made_by,
                     version_needed,
                     disk_number,
                     central_directory_start_disk,
@@ -179,9 +174,10 @@ pub(crate) trait ZipAsyncReadExt {
                     central_directory_size,
                     central_directory_offset,
                     comment,
+                    offset,
                 })
             } else {
-                let _version_made_by = None;
+                let version_made_by = None;
                 let version_needed = None;
                 let disk_number = zip32_record.disk_number as u32;
                 let central_directory_start_disk = zip32_record.central_directory_start_disk as u32;
@@ -189,9 +185,10 @@ pub(crate) trait ZipAsyncReadExt {
                 let number_of_entries = zip32_record.number_of_entries as u64;
                 let central_directory_size = zip32_record.central_directory_size as u64;
                 let central_directory_offset = zip32_record.central_directory_offset as u64;
+                let offset = position + idx_32 as u64;
 
                 Ok(ZipCentralDirectoryEndRecord {
-                    _version_made_by,
+                    version_made_by,
                     version_needed,
                     disk_number,
                     central_directory_start_disk,
@@ -200,6 +197,7 @@ pub(crate) trait ZipAsyncReadExt {
                     central_directory_size,
                     central_directory_offset,
                     comment,
+                    offset,
                 })
             }
         } else {
@@ -209,32 +207,33 @@ pub(crate) trait ZipAsyncReadExt {
         }
     }
 
-    async fn read_zip_entry(
+    async fn read_zip_entry<S>(
         &mut self,
         eocdr: &ZipCentralDirectoryEndRecord,
-    ) -> ZipResult<IndexMap<ZipPath, ZipEntry>>
+        volumes: &Volumes,
+    ) -> ZipResult<ZipIndex<S>>
     where
         Self: AsyncRead + AsyncSeek + Unpin,
+        S: Storage,
     {
         let size = eocdr.central_directory_size;
-        let offset = eocdr.central_directory_offset;
+        let offset = volumes.resolve(
+            eocdr.central_directory_start_disk,
+            eocdr.central_directory_offset,
+        )?;
         let mut buffer = Vec::with_capacity(size as usize);
         let signature = ZipEntry::SIGNATURE.to_le_bytes();
         self.seek(SeekFrom::Start(offset)).await?;
         self.take(size).read_to_end(&mut buffer).await?;
         let multi_idx = buffer.search_all(&signature);
-        let mut map = IndexMap::new();
-        let mut stream = iter(multi_idx);
-
-        while let Some(idx) = stream.next().await {
-            let entry = ZipEntry::try_from(&buffer[idx..])?;
-            let name = entry.file_name.clone();
-            map.extend([(name, entry)]);
+        let mut index = ZipIndex::new(multi_idx.len());
+        for idx in multi_idx {
+            index.push_header(&buffer[idx..])?;
         }
-        Ok(map)
+        Ok(index)
     }
 
-    async fn read_zipfile(&mut self) -> ZipResult<ZipFile>
+    async fn read_local_header(&mut self) -> ZipResult<LocalFileHeader>
     where
         Self: AsyncRead + Unpin,
     {
@@ -246,52 +245,61 @@ pub(crate) trait ZipAsyncReadExt {
             ))?
         }
         let mut buffer = [0; ZipFile::SIZE];
-        self.read(&mut buffer).await?;
-        let datetime: [u8; 4] = buffer[6..10].try_into()?;
-
-        let version_needed = u16::from_le_bytes(buffer[0..2].try_into()?);
-        let flags = GeneralPurposeFlag::from(u16::from_le_bytes(buffer[2..4].try_into()?));
-        let compression = Compression::try_from(u16::from_le_bytes(buffer[4..6].try_into()?))?;
-        let last_mod_datetime = ZipDateTime::try_from(datetime)?;
-        let crc32 = u32::from_le_bytes(buffer[10..14].try_into()?);
-        let compressed_size = u32::from_le_bytes(buffer[14..18].try_into()?);
-        let uncompressed_size = u32::from_le_bytes(buffer[18..22].try_into()?);
+        self.read_exact(&mut buffer).await?;
+        let mut header = LocalFileHeader::try_from(buffer)?;
 
-        let file_name = {
-            let length = u16::from_le_bytes(buffer[22..24].try_into()?) as u64;
-            let mut path = ZipPath::new();
-            self.take(length).read_to_zip_path(&mut path).await?;
-            path
-        };
-        let extra_field: Option<Vec<ExtraField>> = {
-            let length = u16::from_le_bytes(buffer[24..26].try_into()?) as u64;
+        self.take(header.file_name_length as u64)
+            .read_to_zip_path(&mut header.file_name)
+            .await?;
+        if header.extra_field_length > 0 {
+            let mut buffer = Vec::new();
+            self.take(header.extra_field_length as u64)
+                .read_to_end(&mut buffer)
+                .await?;
+            header.zip64 = zip64_block(&buffer).is_some();
+        }
 
-            if length > 0 {
-                let mut buffer = Vec::new();
-                self.take(length).read_to_end(&mut buffer).await?;
-                None
-            } else {
-                None
-            }
-        };
-        let mut data = Vec::with_capacity(compressed_size as usize);
+        Ok(header)
+    }
 
-        self.take(compressed_size as u64)
-            .read_to_end(&mut data)
+    /// Reads a data descriptor, with 8-byte sizes when `zip64` is set.
+    async fn read_data_descriptor(&mut self, zip64: bool) -> ZipResult<(DataDescriptor, u64)>
+    where
+        Self: AsyncRead + Unpin,
+    {
+        let length = match zip64 {
+            true => ZIP64_DATA_DESCRIPTOR_LENGTH,
+            false => DATA_DESCRIPTOR_LENGTH,
+        } as usize;
+        let mut buffer = [0; ZIP64_DATA_DESCRIPTOR_LENGTH as usize];
+        self.read_exact(&mut buffer[..SIGNATURE_LENGTH as usize])
             .await?;
 
-        Ok(ZipFile {
-            version_needed,
-            flags,
-            compression,
-            last_mod_datetime,
-            crc32,
-            compressed_size,
-            uncompressed_size,
-            file_name,
-            extra_field,
-            data,
-        })
+        let signature = u32::from_le_bytes(buffer[..SIGNATURE_LENGTH as usize].try_into()?);
+        let read = if signature == DATA_DESCRIPTOR_SIGNATURE {
+            self.read_exact(&mut buffer[..length]).await?;
+            SIGNATURE_LENGTH as usize + length
+        } else {
+            self.read_exact(&mut buffer[SIGNATURE_LENGTH as usize..length])
+                .await?;
+            length
+        };
+        let descriptor = match zip64 {
+            true => DataDescriptor::try_from(buffer)?,
+            false => DataDescriptor::try_from(TryInto::<[u8; 12]>::try_into(&buffer[..length])?)?,
+        };
+        Ok((descriptor, read as u64))
+    }
+
+    /// Reads the local file of `entry`, with the sizes from the central
+    /// directory since the local header may leave them to a data descriptor.
+    async fn read_zipfile(&mut self, entry: &ZipEntry) -> ZipResult<ZipFile>
+    where
+        Self: AsyncRead + Unpin,
+    {
+        let mut file = ReadZipFile::with_compressed_size(self, entry.compressed_size).await?;
+        file.sizes_from(entry);
+        Ok(file)
     }
 }
 
@@ -323,5 +331,53 @@ impl ZipSpecs for ZipFile {
 }
 
 impl Zip32CentralDirectoryEndRecord {
-    const MAX_SIZE: usize = SIGNATURE_LENGTH as usize + (Self::SIZE as usize + u16::MAX as usize);
+    const MAX_SIZE: usize = SIGNATURE_LENGTH as usize + (Self::SIZE + u16::MAX as usize);
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            testing::{build_archive, build_zip64_archive},
+            ZipArchive,
+        },
+        smol::io::Cursor,
+    };
+
+    #[test]
+    fn read_archive_with_prefix() {
+        smol::block_on(async {
+            let stub = b"#!/bin/sh\nexec unzip \"$0\"\n".to_vec();
+            let files: [(&str, &[u8]); 2] = [("a.txt", b"alpha"), ("b/c.txt", b"charlie")];
+            let archive = [stub.clone(), build_archive(&files)].concat();
+
+            let mut zip = ZipArchive::new(Cursor::new(archive)).await.unwrap();
+            assert_eq!(zip.prefix_len(), stub.len() as u64);
+            assert_eq!(zip.prefix().await.unwrap(), stub);
+            for (name, data) in files {
+                assert_eq!(&*zip.file_by_name(name).await.unwrap(), data);
+            }
+
+            let zip = ZipArchive::new(Cursor::new(build_archive(&files)))
+                .await
+                .unwrap();
+            assert_eq!(zip.prefix_len(), 0);
+        })
+    }
+
+    #[test]
+    fn read_zip64_archive_with_prefix() {
+        smol::block_on(async {
+            let stub = b"#!/bin/sh\nexec unzip \"$0\"\n".to_vec();
+            let files: [(&str, &[u8]); 2] = [("a.txt", b"alpha"), ("b/c.txt", b"charlie")];
+            for comment_len in [0, u16::MAX - 40, u16::MAX] {
+                let archive = [stub.clone(), build_zip64_archive(&files, comment_len)].concat();
+                let mut zip = ZipArchive::new(Cursor::new(archive)).await.unwrap();
+                assert_eq!(zip.prefix_len(), stub.len() as u64);
+                for (name, data) in files {
+                    assert_eq!(&*zip.file_by_name(name).await.unwrap(), data);
+                }
+            }
+        })
+    }
 }
@@ -0,0 +1,388 @@
+use {
+    crate::{
+        error::{ZipError, ZipResult},
+        ZipArchive,
+    },
+    futures_lite::{
+        io::{AsyncRead, AsyncSeek},
+        stream::StreamExt,
+    },
+    std::{
+        collections::{HashMap, VecDeque},
+        ffi::OsStr,
+        fs, io,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Links followed while resolving a target before giving up, as on Linux.
+const MAX_LINK_HOPS: usize = 40;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SymlinkPolicy {
+    /// Links are created as symlinks with their stored target.
+    #[default]
+    Create,
+    /// Links are left out.
+    Skip,
+    /// A copy of what the link points at is written in its place. Links whose
+    /// target is missing are left out.
+    Follow,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExtractOptions {
+    symlinks: SymlinkPolicy,
+}
+
+impl ExtractOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn symlinks(mut self, symlinks: SymlinkPolicy) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+}
+
+impl<R> ZipArchive<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    /// Target of the symlink entry `path`, or `None` when it is not a symlink.
+    pub async fn symlink_target<S>(&mut self, path: S) -> ZipResult<Option<String>>
+    where
+        S: AsRef<OsStr>,
+    {
+        match self.index_of(path.as_ref()) {
+            Some(index) => self.symlink_target_by_index(index).await,
+            None => Err(ZipError::InvalidArchive("Invalid Key".into())),
+        }
+    }
+
+    pub async fn symlink_target_by_index(&mut self, index: usize) -> ZipResult<Option<String>> {
+        match self.entries.get_index(index) {
+            Some(entry) if entry.file_name.is_symlink() => {}
+            Some(_) => return Ok(None),
+            None => Err(ZipError::InvalidArchive("Invalid Index".into()))?,
+        }
+        let data = self.file_by_index(index).await?.extract().await?;
+        let target = String::from_utf8(data)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(Some(target))
+    }
+
+    /// Writes every entry below `dest` at its sanitized path, in stored order.
+    /// Symlinks are handled once everything else is written, and any whose
+    /// target resolves outside `dest` is refused with
+    /// [`ZipError::UnsafeSymlink`]. Returns the number of entries written.
+    pub async fn extract_to<P>(&mut self, dest: P, options: &ExtractOptions) -> ZipResult<usize>
+    where
+        P: AsRef<Path>,
+    {
+        let dest = dest.as_ref();
+        fs::create_dir_all(dest)?;
+
+        let mut written = 0;
+        let mut links = Vec::new();
+        let mut stream = self.stream();
+        while let Some(file) = stream.next().await {
+            let file = file?;
+            let path = file.sanitized_path();
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+
+            if file.file_name.is_symlink() {
+                let target = String::from_utf8(file.extract().await?)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                links.push((path, target));
+                continue;
+            } else if file.is_dir() {
+                fs::create_dir_all(dest.join(&path))?;
+            } else {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(dest.join(parent))?;
+                }
+                fs::write(dest.join(&path), file.extract().await?)?;
+            }
+            written += 1;
+        }
+
+        let follow = match options.symlinks {
+            SymlinkPolicy::Create => false,
+            SymlinkPolicy::Skip => return Ok(written),
+            SymlinkPolicy::Follow => true,
+        };
+        // Every link is checked before any is written.
+        let targets: HashMap<PathBuf, String> = links.iter().cloned().collect();
+        let resolved = links
+            .iter()
+            .map(|(path, target)| {
+                resolve(&targets, path, target).ok_or_else(|| ZipError::UnsafeSymlink(path.clone()))
+            })
+            .collect::<ZipResult<Vec<_>>>()?;
+
+        let order = match follow {
+            true => copy_order(&links, &resolved)?,
+            false => (0..links.len()).collect(),
+        };
+        for index in order {
+            let ((path, target), resolved) = (&links[index], &resolved[index]);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(dest.join(parent))?;
+            }
+            if !follow {
+                symlink(target, &dest.join(path), dest.join(resolved).is_dir())?;
+            } else if !dest.join(resolved).exists() {
+                continue;
+            } else {
+                copy(&dest.join(resolved), &dest.join(path))?;
+            }
+            written += 1;
+        }
+        Ok(written)
+    }
+}
+
+/// Resolves the `target` of the link at `link` relative to the root, going
+/// through the other links of the archive, or `None` when it leaves the root.
+/// A link below another link is refused, since it would be written through it.
+fn resolve(links: &HashMap<PathBuf, String>, link: &Path, target: &str) -> Option<PathBuf> {
+    if link
+        .ancestors()
+        .skip(1)
+        .any(|parent| links.contains_key(parent))
+    {
+        return None;
+    }
+    let mut path = PathBuf::new();
+    let mut pending = VecDeque::new();
+    prepend(&mut pending, target)?;
+    if let Some(parent) = link.parent() {
+        for component in parent.components().rev() {
+            pending.push_front(component.as_os_str().to_string_lossy().into_owned());
+        }
+    }
+
+    let mut hops = 0;
+    while let Some(component) = pending.pop_front() {
+        match component.as_str() {
+            "" | "." => {}
+            ".." => {
+                if !path.pop() {
+                    return None;
+                }
+            }
+            name => {
+                path.push(name);
+                if let Some(target) = links.get(&path) {
+                    hops += 1;
+                    if hops > MAX_LINK_HOPS {
+                        return None;
+                    }
+                    path.pop();
+                    prepend(&mut pending, target)?;
+                }
+            }
+        }
+    }
+    Some(path)
+}
+
+fn prepend(pending: &mut VecDeque<String>, target: &str) -> Option<()> {
+    let target = target.replace('\\', "/");
+    let drive = matches!(target.as_bytes(), [drive, b':', ..] if drive.is_ascii_alphabetic());
+    if drive || target.starts_with('/') {
+        return None;
+    }
+    for component in target.rsplit('/') {
+        pending.push_front(component.to_owned());
+    }
+    Some(())
+}
+
+/// Order in which links are copied so that a link to a directory is copied
+/// after the links inside that directory.
+fn copy_order(links: &[(PathBuf, String)], resolved: &[PathBuf]) -> ZipResult<Vec<usize>> {
+    let inside = |index: usize, other: usize| links[other].0.starts_with(&resolved[index]);
+    if let Some(index) = (0..links.len()).find(|&index| inside(index, index)) {
+        let path = &links[index].0;
+        Err(ZipError::InvalidArchive(
+            format!("{path:?} links to a directory containing it").into(),
+        ))?
+    }
+
+    let mut pending: Vec<usize> = (0..links.len()).collect();
+    let mut order = Vec::with_capacity(links.len());
+    while !pending.is_empty() {
+        let Some(position) = pending
+            .iter()
+            .position(|&index| !pending.iter().any(|&other| inside(index, other)))
+        else {
+            Err(ZipError::InvalidArchive(
+                "Links copy directories containing each other".into(),
+            ))?
+        };
+        order.push(pending.remove(position));
+    }
+    Ok(order)
+}
+
+fn copy(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::metadata(from)?.is_dir() {
+        fs::create_dir_all(to)?;
+        for child in fs::read_dir(from)? {
+            let child = child?;
+            copy(&child.path(), &to.join(child.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &str, link: &Path, _is_dir: bool) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &str, link: &Path, is_dir: bool) -> io::Result<()> {
+    match is_dir {
+        true => std::os::windows::fs::symlink_dir(target, link),
+        false => std::os::windows::fs::symlink_file(target, link),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn symlink(_target: &str, _link: &Path, _is_dir: bool) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{resolve, ExtractOptions, SymlinkPolicy},
+        crate::{
+            error::ZipError,
+            testing::TempDir,
+            write::{FileOptions, ZipWriter},
+            ZipArchive,
+        },
+        futures_lite::{future::block_on, io::Cursor},
+        std::{collections::HashMap, fs, path::Path},
+    };
+
+    #[test]
+    fn resolve_links_within_the_root() {
+        let links: HashMap<_, _> = [
+            ("a/up".into(), "..".to_string()),
+            ("loop".into(), "loop".to_string()),
+            ("d1/d2/a".into(), "../..".to_string()),
+            ("d1/d2/a/x".into(), "../../../escape".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let resolve = |link: &str, target: &str| resolve(&links, Path::new(link), target);
+
+        assert_eq!(resolve("a/b", "../c/d"), Some("c/d".into()));
+        assert_eq!(resolve("a/up", ".."), Some("".into()));
+        assert_eq!(resolve("x", "a/up/c"), Some("c".into()));
+        assert_eq!(resolve("x", "a/up/../.."), None);
+        assert_eq!(resolve("a/b", "../../c"), None);
+        assert_eq!(resolve("x", "/etc/passwd"), None);
+        assert_eq!(resolve("x", "C:\\Windows"), None);
+        assert_eq!(resolve("x", "loop"), None);
+        assert_eq!(resolve("d1/d2/a", "../.."), Some("".into()));
+        assert_eq!(resolve("d1/d2/a/x", "../../../escape"), None);
+        assert_eq!(resolve("a/up/b", "c"), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn extract_symlinks_by_policy() {
+        let dest = TempDir::new("extract");
+        let archive = |links: &[(&str, &str)]| {
+            block_on(async {
+                let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+                let options = FileOptions::new();
+                writer.create_dir("docs", options.clone()).await.unwrap();
+                writer
+                    .write_file("docs/a.txt", b"alpha", options.clone())
+                    .await
+                    .unwrap();
+                for (name, target) in links {
+                    writer
+                        .create_symlink(name, target, options.clone())
+                        .await
+                        .unwrap();
+                }
+                let archive = writer.finish().await.unwrap().into_inner();
+                ZipArchive::new(Cursor::new(archive)).await.unwrap()
+            })
+        };
+        let links = [
+            ("all", "docs"),
+            ("docs/link", "a.txt"),
+            ("docs/dangling", "missing.txt"),
+        ];
+
+        block_on(async {
+            let mut zip = archive(&links);
+            assert_eq!(
+                zip.symlink_target("all").await.unwrap().as_deref(),
+                Some("docs")
+            );
+            assert_eq!(zip.symlink_target("docs/a.txt").await.unwrap(), None);
+
+            for policy in [
+                SymlinkPolicy::Create,
+                SymlinkPolicy::Skip,
+                SymlinkPolicy::Follow,
+            ] {
+                let _ = fs::remove_dir_all(&*dest);
+                let options = ExtractOptions::new().symlinks(policy);
+                let written = zip.extract_to(&*dest, &options).await.unwrap();
+                assert_eq!(fs::read(dest.join("docs/a.txt")).unwrap(), b"alpha");
+
+                let link = fs::symlink_metadata(dest.join("docs/link"));
+                match policy {
+                    SymlinkPolicy::Create => {
+                        assert_eq!(written, 5);
+                        assert!(link.unwrap().is_symlink());
+                        assert_eq!(fs::read_link(dest.join("all")).unwrap(), Path::new("docs"));
+                    }
+                    SymlinkPolicy::Skip => {
+                        assert_eq!(written, 2);
+                        assert!(link.is_err());
+                    }
+                    SymlinkPolicy::Follow => {
+                        assert_eq!(written, 4);
+                        assert!(link.unwrap().is_file());
+                        assert!(fs::symlink_metadata(dest.join("all")).unwrap().is_dir());
+                        assert_eq!(fs::read(dest.join("all/link")).unwrap(), b"alpha");
+                        assert!(fs::symlink_metadata(dest.join("docs/dangling")).is_err());
+                    }
+                }
+            }
+
+            let mut zip = archive(&[("docs/escape", "../../outside")]);
+            let _ = fs::remove_dir_all(&*dest);
+            let result = zip.extract_to(&*dest, &ExtractOptions::new()).await;
+            assert!(
+                matches!(result, Err(ZipError::UnsafeSymlink(path)) if path == Path::new("docs/escape"))
+            );
+            assert!(fs::symlink_metadata(dest.join("docs/escape")).is_err());
+
+            let mut zip = archive(&[("d1/d2/a", "../.."), ("d1/d2/a/x", "../../../escape")]);
+            let _ = fs::remove_dir_all(&*dest);
+            let result = zip.extract_to(&*dest, &ExtractOptions::new()).await;
+            assert!(
+                matches!(result, Err(ZipError::UnsafeSymlink(path)) if path == Path::new("d1/d2/a/x"))
+            );
+            assert!(fs::symlink_metadata(dest.join("x")).is_err());
+        });
+    }
+}
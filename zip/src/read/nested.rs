@@ -0,0 +1,256 @@
+use {
+    crate::{
+        compression::Compression,
+        error::{ZipError, ZipResult},
+        read::ZipAsyncReadExt,
+        specs::ZipEntry,
+        ZipArchive,
+    },
+    futures_lite::{
+        io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, Cursor, SeekFrom},
+        ready,
+    },
+    std::{
+        ffi::OsStr,
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+    },
+};
+
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 8;
+
+pub struct EntryReader<'a, R> {
+    reader: &'a mut R,
+    start: u64,
+    len: u64,
+    position: u64,
+    synced: bool,
+}
+
+pub enum NestedReader<'a, R> {
+    Stored(EntryReader<'a, R>),
+    Buffered(Cursor<Vec<u8>>),
+}
+
+impl<'a, R> EntryReader<'a, R> {
+    pub(crate) fn new(reader: &'a mut R, start: u64, len: u64) -> Self {
+        Self {
+            reader,
+            start,
+            len,
+            position: 0,
+            synced: false,
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<R> AsyncRead for EntryReader<'_, R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.position >= this.len || buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        if !this.synced {
+            let offset = this.start + this.position;
+            ready!(Pin::new(&mut *this.reader).poll_seek(cx, SeekFrom::Start(offset)))?;
+            this.synced = true;
+        }
+
+        let max = (this.len - this.position).min(buf.len() as u64) as usize;
+        let read = ready!(Pin::new(&mut *this.reader).poll_read(cx, &mut buf[..max]))?;
+        this.position += read as u64;
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl<R> AsyncSeek for EntryReader<'_, R>
+where
+    R: AsyncSeek + Unpin,
+{
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => this.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => this.position.checked_add_signed(offset),
+        };
+
+        match position {
+            Some(position) => {
+                if position != this.position {
+                    this.position = position;
+                    this.synced = false;
+                }
+                Poll::Ready(Ok(position))
+            }
+            None => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            ))),
+        }
+    }
+}
+
+impl<R> AsyncRead for NestedReader<'_, R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Stored(reader) => Pin::new(reader).poll_read(cx, buf),
+            Self::Buffered(reader) => Pin::new(reader).poll_read(cx, buf),
+        }
+    }
+}
+
+impl<R> AsyncSeek for NestedReader<'_, R>
+where
+    R: AsyncSeek + Unpin,
+{
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        match self.get_mut() {
+            Self::Stored(reader) => Pin::new(reader).poll_seek(cx, pos),
+            Self::Buffered(reader) => Pin::new(reader).poll_seek(cx, pos),
+        }
+    }
+}
+
+impl<R> ZipArchive<R> {
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.max_depth = depth;
+    }
+}
+
+impl<R> ZipArchive<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    pub async fn open_nested<S>(&mut self, path: S) -> ZipResult<ZipArchive<NestedReader<'_, R>>>
+    where
+        S: AsRef<OsStr>,
+    {
+        match self.index_of(path.as_ref()) {
+            Some(index) => self.open_nested_by_index(index).await,
+            None => Err(ZipError::InvalidArchive("Invalid Key".into())),
+        }
+    }
+
+    pub async fn open_nested_by_index(
+        &mut self,
+        index: usize,
+    ) -> ZipResult<ZipArchive<NestedReader<'_, R>>> {
+        if self.depth >= self.max_depth {
+            Err(ZipError::NestingLimitExceeded(self.max_depth))?
+        }
+        let entry = match self.entries.get_index(index) {
+            Some(value) => value.into_owned(),
+            None => Err(ZipError::InvalidArchive("Invalid Index".into()))?,
+        };
+
+        let offset = self
+            .volumes
+            .resolve(entry.disk_start as u32, entry.file_header_offset)?;
+        self.reader.seek(SeekFrom::Start(offset)).await?;
+        let header = self.reader.read_local_header().await?;
+
+        let reader = if is_seekable(&entry) {
+            let start = offset + header.length;
+            let len = entry.compressed_size;
+            NestedReader::Stored(EntryReader::new(&mut self.reader, start, len))
+        } else {
+            let mut data = Vec::with_capacity(entry.compressed_size as usize);
+            (&mut self.reader)
+                .take(entry.compressed_size)
+                .read_to_end(&mut data)
+                .await?;
+            let mut file = header.into_zipfile(data);
+            file.compression = entry.compression;
+            file.sizes_from(&entry);
+            NestedReader::Buffered(Cursor::new(file.extract().await?))
+        };
+
+        let (depth, max_depth) = (self.depth + 1, self.max_depth);
+        let mut archive = ZipArchive::new(reader).await?;
+        archive.depth = depth;
+        archive.max_depth = max_depth;
+        Ok(archive)
+    }
+}
+
+fn is_seekable(entry: &ZipEntry) -> bool {
+    entry.compression == Compression::Stored && !entry.is_encrypted()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{error::ZipError, testing::build_archive, ZipArchive},
+        smol::io::Cursor,
+    };
+
+    #[test]
+    fn open_nested_archives() {
+        smol::block_on(async {
+            let inner = build_archive(&[("deep.txt", b"bottom")]);
+            let middle = build_archive(&[("readme.txt", b"middle"), ("inner.zip", &inner)]);
+            let outer = build_archive(&[("middle.zip", &middle)]);
+
+            let mut zip = ZipArchive::new(Cursor::new(outer)).await.unwrap();
+            let mut middle = zip.open_nested("middle.zip").await.unwrap();
+            assert_eq!(middle.depth(), 1);
+            assert_eq!(
+                &*middle.file_by_name("readme.txt").await.unwrap(),
+                b"middle"
+            );
+
+            let mut inner = middle.open_nested("inner.zip").await.unwrap();
+            assert_eq!(&*inner.file_by_name("deep.txt").await.unwrap(), b"bottom");
+            drop(inner);
+            drop(middle);
+
+            zip.set_max_depth(1);
+            let mut middle = zip.open_nested("middle.zip").await.unwrap();
+            assert!(matches!(
+                middle.open_nested("inner.zip").await,
+                Err(ZipError::NestingLimitExceeded(1))
+            ));
+        })
+    }
+}
@@ -1,149 +1,270 @@
 use {
     crate::{
-        datetime::ZipDateTime,
         error::{ZipError, ZipResult},
         path::ZipPath,
-        specs::{compression::Compression, extra_field::ExtraField, GeneralPurposeFlag, ZipSpecs},
+        specs::{LocalFileHeader, ZipSpecs, SIGNATURE_LENGTH},
         ZipFile,
     },
-    smol::{
-        io::{AsyncRead, AsyncReadExt, AsyncSeek, SeekFrom},
-        ready,
+    futures_lite::{io::AsyncRead, ready},
+    std::{
+        future::Future,
+        io, mem,
+        pin::Pin,
+        task::{Context, Poll},
     },
-    std::{io, pin, task},
 };
 
-pub(crate) trait ZipPollReadExt {
-    fn poll_read_u32_le(
-        self: pin::Pin<&mut Self>,
-        cx: &mut task::Context<'_>,
-    ) -> task::Poll<Result<u32, io::Error>>
-    where
-        Self: AsyncRead,
-    {
-        let mut buffer = [0; 4];
-        match ready!(self.poll_read(cx, &mut buffer)) {
-            Ok(_) => task::Poll::Ready(Ok(u32::from_le_bytes(buffer))),
-            Err(err) => task::Poll::Ready(Err(err)),
+const CHUNK_SIZE: usize = 64 * 1024;
+const HEADER_SIZE: usize = SIGNATURE_LENGTH as usize + ZipFile::SIZE;
+
+enum State {
+    Header,
+    FileName(LocalFileHeader),
+    Data(LocalFileHeader),
+    Done,
+}
+
+pub struct ReadZipFile<R> {
+    reader: R,
+    state: State,
+    buffer: Vec<u8>,
+    filled: usize,
+    target: usize,
+    compressed_size: Option<u64>,
+}
+
+impl<R> ReadZipFile<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            state: State::Header,
+            buffer: Vec::new(),
+            filled: 0,
+            target: HEADER_SIZE,
+            compressed_size: None,
+        }
+    }
+
+    /// Reads `compressed_size` bytes of data, as known from the central
+    /// directory, instead of the size in the local header, which is zero when
+    /// a data descriptor follows and a placeholder for zip64 entries.
+    pub fn with_compressed_size(reader: R, compressed_size: u64) -> Self {
+        Self {
+            compressed_size: Some(compressed_size),
+            ..Self::new(reader)
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+
+    fn take_buffer(&mut self, target: usize) -> Vec<u8> {
+        let mut buffer = mem::take(&mut self.buffer);
+        buffer.truncate(self.filled);
+        self.filled = 0;
+        self.target = target;
+        buffer
+    }
+}
+
+impl<R> ReadZipFile<R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.filled < self.target {
+            if self.filled == self.buffer.len() {
+                let grow = (self.target - self.filled).min(CHUNK_SIZE);
+                self.buffer.resize(self.filled + grow, 0);
+            }
+
+            let read =
+                ready!(Pin::new(&mut self.reader).poll_read(cx, &mut self.buffer[self.filled..]))?;
+            if read == 0 {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            self.filled += read;
         }
+        Poll::Ready(Ok(()))
     }
 
-    fn poll_read_zipfile(
-        mut self: pin::Pin<&mut Self>,
-        cx: &mut task::Context<'_>,
-    ) -> task::Poll<ZipResult<ZipFile>>
-    where
-        Self: AsyncRead + AsyncSeek,
-    {
-        match ready!(self.as_mut().poll_read_u32_le(cx)) {
-            Ok(signature) => {
+    fn advance(&mut self, state: State) -> ZipResult<Option<ZipFile>> {
+        match state {
+            State::Header => {
+                let buffer = self.take_buffer(0);
+                let signature = u32::from_le_bytes(buffer[..SIGNATURE_LENGTH as usize].try_into()?);
                 if signature != ZipFile::SIGNATURE {
-                    return task::Poll::Ready(Err(ZipError::SignatureNotFound(
+                    Err(ZipError::SignatureNotFound(
                         "Local File Header Signature not found".into(),
-                    )));
-                }
-                let mut buffer = [0; ZipFile::SIZE];
-                match ready!(self.as_mut().poll_read(cx, &mut buffer)) {
-                    Ok(_) => {
-                        let datetime: [u8; 4] = buffer[6..10].try_into().unwrap();
-
-                        let version_needed = u16::from_le_bytes(buffer[0..2].try_into().unwrap());
-                        let flags = GeneralPurposeFlag::from(u16::from_le_bytes(
-                            buffer[2..4].try_into().unwrap(),
-                        ));
-                        let compression = Compression::try_from(u16::from_le_bytes(
-                            buffer[4..6].try_into().unwrap(),
-                        ))
-                        .unwrap();
-                        let last_mod_datetime = ZipDateTime::try_from(datetime).unwrap();
-                        let crc32 = u32::from_le_bytes(buffer[10..14].try_into().unwrap());
-                        let compressed_size =
-                            u32::from_le_bytes(buffer[14..18].try_into().unwrap());
-                        let uncompressed_size =
-                            u32::from_le_bytes(buffer[18..22].try_into().unwrap());
-                        let file_name = {
-                            let length = u16::from_le_bytes(buffer[22..24].try_into().unwrap());
-                            let mut buffer = Vec::with_capacity(length as usize);
-                            let mut taker = self.as_mut().take(length as u64);
-                            let mut reader = pin::Pin::new(&mut taker);
-                            let mut i_buffer = [0; 2048];
-                            loop {
-                                let slice = &mut i_buffer[..];
-                                match ready!(reader.as_mut().poll_read(cx, slice)) {
-                                    Ok(read) => {
-                                        if read != 0 {
-                                            buffer.extend_from_slice(slice)
-                                        } else {
-                                            break;
-                                        }
-                                    }
-                                    Err(err) => return task::Poll::Ready(Err(ZipError::from(err))),
-                                }
-                            }
-                            let string = String::from_utf8(buffer);
-                            match string {
-                                Ok(string) => ZipPath::from(string),
-                                Err(err) => {
-                                    return task::Poll::Ready(Err(ZipError::from(io::Error::new(
-                                        io::ErrorKind::InvalidData,
-                                        err,
-                                    ))))
-                                }
-                            }
-                        };
-                        let extra_field: Option<Vec<ExtraField>> = {
-                            let length = u16::from_le_bytes(buffer[24..26].try_into().unwrap());
-                            match ready!(self
-                                .as_mut()
-                                .poll_seek(cx, SeekFrom::Current(length as i64)))
-                            {
-                                Ok(_) => None,
-                                Err(err) => return task::Poll::Ready(Err(ZipError::from(err))),
-                            }
-                        };
-
-                        let mut data = Vec::with_capacity(compressed_size as usize);
-
-                        let mut taker = self.take(compressed_size as u64);
-                        let mut reader = pin::Pin::new(&mut taker);
-                        let mut i_buffer = [0; 2048];
-                        loop {
-                            let slice = &mut i_buffer[..];
-                            match ready!(reader.as_mut().poll_read(cx, slice)) {
-                                Ok(read) => {
-                                    if read != 0 {
-                                        data.extend_from_slice(slice)
-                                    } else {
-                                        break;
-                                    }
-                                }
-                                Err(err) => return task::Poll::Ready(Err(ZipError::from(err))),
-                            }
-                        }
-                        task::Poll::Ready(Ok(ZipFile {
-                            version_needed,
-                            flags,
-                            compression,
-                            last_mod_datetime,
-                            crc32,
-                            compressed_size,
-                            uncompressed_size,
-                            file_name,
-                            extra_field,
-                            data,
-                        }))
-                    }
-                    Err(err) => task::Poll::Ready(Err(ZipError::from(err))),
+                    ))?
                 }
+
+                let header = LocalFileHeader::try_from(<[u8; ZipFile::SIZE]>::try_from(
+                    &buffer[SIGNATURE_LENGTH as usize..],
+                )?)?;
+                self.target = header.file_name_length as usize + header.extra_field_length as usize;
+                self.state = State::FileName(header);
+                Ok(None)
+            }
+            State::FileName(mut header) => {
+                let compressed_size = self
+                    .compressed_size
+                    .unwrap_or(header.compressed_size as u64);
+                let mut buffer = self.take_buffer(compressed_size as usize);
+                buffer.truncate(header.file_name_length as usize);
+                header.file_name = ZipPath::from_bytes(&buffer);
+                self.state = State::Data(header);
+                Ok(None)
             }
-            Err(err) => task::Poll::Ready(Err(ZipError::from(err))),
+            State::Data(header) => {
+                let data = self.take_buffer(0);
+                Ok(Some(header.into_zipfile(data)))
+            }
+            State::Done => Err(ZipError::InvalidArchive(
+                "ReadZipFile polled after completion".into(),
+            )),
         }
     }
 }
 
-impl<T> ZipPollReadExt for T
+impl<R> Future for ReadZipFile<R>
 where
-    T: AsyncRead,
-    for<'a> pin::Pin<&'a mut T>: AsyncRead,
+    R: AsyncRead + Unpin,
 {
+    type Output = ZipResult<ZipFile>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        loop {
+            if !this.is_terminated() {
+                if let Err(err) = ready!(this.poll_fill(cx)) {
+                    this.state = State::Done;
+                    return Poll::Ready(Err(err.into()));
+                }
+            }
+
+            let state = mem::replace(&mut this.state, State::Done);
+            match this.advance(state) {
+                Ok(Some(file)) => return Poll::Ready(Ok(file)),
+                Ok(None) => (),
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::ReadZipFile,
+        crate::{
+            compression::Compression,
+            error::ZipError,
+            testing::{build_archive, streamed_entry},
+        },
+        futures_lite::{future, io::AsyncRead},
+        std::{
+            future::Future,
+            io,
+            path::Path,
+            pin::Pin,
+            task::{Context, Poll},
+        },
+    };
+
+    struct Trickle {
+        data: Vec<u8>,
+        position: usize,
+        pending: bool,
+    }
+
+    impl AsyncRead for Trickle {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            self.pending = !self.pending;
+            if self.pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            let read = buf.len().min(3).min(self.data.len() - self.position);
+            buf[..read].copy_from_slice(&self.data[self.position..self.position + read]);
+            self.position += read;
+            Poll::Ready(Ok(read))
+        }
+    }
+
+    #[test]
+    fn resume_across_pending_reads() {
+        let archive = build_archive(&[("dir/../notes.txt", b"partial reads")]);
+        let mut reader = Trickle {
+            data: archive,
+            position: 0,
+            pending: false,
+        };
+
+        let mut read = ReadZipFile::new(&mut reader);
+        let mut pending = 0;
+        let file = future::block_on(future::poll_fn(|cx| {
+            let poll = Pin::new(&mut read).poll(cx);
+            pending += poll.is_pending() as usize;
+            poll
+        }))
+        .unwrap();
+
+        assert!(pending > 1);
+        assert_eq!(file.raw_name(), b"dir/../notes.txt");
+        assert_eq!(file.sanitized_path(), Path::new("dir/notes.txt"));
+        assert_eq!(&*file, b"partial reads");
+        assert!(matches!(
+            future::block_on(read),
+            Err(ZipError::InvalidArchive(_))
+        ));
+    }
+
+    #[test]
+    fn read_sizes_from_the_central_directory() {
+        let mut reader = Trickle {
+            data: streamed_entry("d.txt", b"delta"),
+            position: 0,
+            pending: false,
+        };
+        let file = future::block_on(ReadZipFile::with_compressed_size(&mut reader, 5)).unwrap();
+        assert_eq!(file.raw_name(), b"d.txt");
+        assert_eq!(&*file, b"delta");
+    }
+
+    #[test]
+    fn reject_truncated_and_unsupported_entries() {
+        let archive = build_archive(&[("a.txt", b"truncated")]);
+        let result = future::block_on(ReadZipFile::new(&archive[..35]));
+        assert!(
+            matches!(result, Err(ZipError::IO(err)) if err.kind() == io::ErrorKind::UnexpectedEof)
+        );
+
+        let mut archive = build_archive(&[("a.txt", b"unsupported")]);
+        archive[8..10].copy_from_slice(&7u16.to_le_bytes());
+        let file = future::block_on(ReadZipFile::new(&archive[..])).unwrap();
+        assert_eq!(file.compression, Compression::Other(7));
+        assert!(matches!(
+            future::block_on(file.extract()),
+            Err(ZipError::CompressionNotSupported)
+        ));
+    }
 }
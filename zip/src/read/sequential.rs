@@ -0,0 +1,222 @@
+use {
+    crate::{
+        error::{ZipError, ZipResult},
+        path::ZipPath,
+        read::ZipAsyncReadExt,
+        specs::{LocalFileHeader, ZipEntry, ZipSpecs, SIGNATURE_LENGTH},
+        ZipFile,
+    },
+    futures_lite::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, SeekFrom},
+    std::io,
+};
+
+pub(crate) const DEFAULT_READ_AHEAD: u64 = 1024 * 1024;
+const HEADER_SIZE: usize = SIGNATURE_LENGTH as usize + ZipFile::SIZE;
+
+/// Keeps a window of the archive in memory so that entries stored next to each
+/// other are fetched with one large read instead of a seek and several small
+/// reads per entry. Only moves the underlying reader backwards or over a gap.
+pub(crate) struct ReadAhead<'a, R> {
+    reader: &'a mut R,
+    buffer: Vec<u8>,
+    size: u64,
+    start: u64,
+    positioned: bool,
+}
+
+impl<'a, R> ReadAhead<'a, R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    pub(crate) fn new(reader: &'a mut R, size: u64) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            size,
+            start: 0,
+            positioned: false,
+        }
+    }
+
+    /// Reads the local file of `entry` at `offset`, with the sizes from the
+    /// central directory as the local header may leave them to a data
+    /// descriptor.
+    pub(crate) async fn read_zipfile(
+        &mut self,
+        offset: u64,
+        entry: &ZipEntry,
+    ) -> ZipResult<ZipFile> {
+        if self.size == 0 {
+            self.reader.seek(SeekFrom::Start(offset)).await?;
+            return self.reader.read_zipfile(entry).await;
+        }
+
+        let buffer = self.fill(offset, HEADER_SIZE as u64).await?;
+        let signature = u32::from_le_bytes(buffer[..SIGNATURE_LENGTH as usize].try_into()?);
+        if signature != ZipFile::SIGNATURE {
+            Err(ZipError::SignatureNotFound(
+                "Local File Header Signature not found".into(),
+            ))?
+        }
+        let mut header = LocalFileHeader::try_from(<[u8; ZipFile::SIZE]>::try_from(
+            &buffer[SIGNATURE_LENGTH as usize..],
+        )?)?;
+
+        let buffer = self
+            .fill(offset, header.length + entry.compressed_size)
+            .await?;
+        let name = &buffer[HEADER_SIZE..HEADER_SIZE + header.file_name_length as usize];
+        header.file_name = ZipPath::from_bytes(name);
+        let data = buffer[header.length as usize..].to_vec();
+        let mut file = header.into_zipfile(data);
+        file.sizes_from(entry);
+        Ok(file)
+    }
+
+    async fn fill(&mut self, offset: u64, length: u64) -> ZipResult<&[u8]> {
+        let end = self.start + self.buffer.len() as u64;
+        if !self.positioned || offset < self.start || offset > end {
+            self.reader.seek(SeekFrom::Start(offset)).await?;
+            self.buffer.clear();
+            self.start = offset;
+            self.positioned = true;
+        } else if offset + length > end {
+            // Drop what was consumed before reading on from the current position.
+            self.buffer.drain(..(offset - self.start) as usize);
+            self.start = offset;
+        }
+
+        let skip = (offset - self.start) as usize;
+        let missing = (skip as u64 + length).saturating_sub(self.buffer.len() as u64);
+        if missing > 0 {
+            let amount = missing.max(self.size);
+            self.buffer.reserve(amount as usize);
+            (&mut *self.reader)
+                .take(amount)
+                .read_to_end(&mut self.buffer)
+                .await?;
+            if self.buffer.len() < skip + length as usize {
+                Err(io::Error::from(io::ErrorKind::UnexpectedEof))?
+            }
+        }
+        Ok(&self.buffer[skip..skip + length as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{ReadAhead, DEFAULT_READ_AHEAD},
+        crate::{
+            error::ZipError,
+            testing::{build_archive, build_zip64_descriptor_archive},
+            ZipArchive,
+        },
+        futures_lite::{
+            future,
+            io::{AsyncRead, AsyncSeek, Cursor, SeekFrom},
+            stream::StreamExt,
+        },
+        std::{
+            io,
+            pin::Pin,
+            task::{Context, Poll},
+        },
+    };
+
+    struct Counting {
+        inner: Cursor<Vec<u8>>,
+        reads: usize,
+        seeks: usize,
+    }
+
+    impl AsyncRead for Counting {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            self.reads += 1;
+            Pin::new(&mut self.inner).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncSeek for Counting {
+        fn poll_seek(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            pos: SeekFrom,
+        ) -> Poll<io::Result<u64>> {
+            self.seeks += 1;
+            Pin::new(&mut self.inner).poll_seek(cx, pos)
+        }
+    }
+
+    #[test]
+    fn stream_entries_with_one_seek() {
+        future::block_on(async {
+            let names: Vec<String> = (0..64).map(|idx| format!("file{idx:02}.txt")).collect();
+            let files: Vec<(&str, &[u8])> = names
+                .iter()
+                .map(|name| (name.as_str(), name.as_bytes()))
+                .collect();
+            let reader = Counting {
+                inner: Cursor::new(build_archive(&files)),
+                reads: 0,
+                seeks: 0,
+            };
+
+            let mut zip = ZipArchive::new(reader).await.unwrap();
+            (zip.reader.reads, zip.reader.seeks) = (0, 0);
+            let streamed: Vec<_> = zip.stream().map(Result::unwrap).collect().await;
+            assert_eq!(streamed.len(), names.len());
+            for (file, name) in streamed.iter().zip(&names) {
+                assert_eq!(file.file_name.as_os_str(), name.as_str());
+                assert_eq!(&**file, name.as_bytes());
+            }
+            assert_eq!(zip.reader.seeks, 1);
+            assert!(zip.reader.reads <= 3);
+
+            zip.set_read_ahead(0);
+            zip.reader.seeks = 0;
+            let streamed: Vec<_> = zip.stream().map(Result::unwrap).collect().await;
+            assert_eq!(streamed.len(), names.len());
+            assert_eq!(zip.reader.seeks, names.len());
+        })
+    }
+
+    #[test]
+    fn read_entries_out_of_order() {
+        future::block_on(async {
+            let archive = build_archive(&[("a.txt", b"alpha"), ("b.txt", b"bravo")]);
+            let second = 30 + 5 + 5;
+            let mut zip = ZipArchive::new(Cursor::new(archive)).await.unwrap();
+            let alpha = zip.entry_by_index(0).cloned().unwrap();
+            let bravo = zip.entry_by_index(1).cloned().unwrap();
+            let mut reader = ReadAhead::new(&mut zip.reader, DEFAULT_READ_AHEAD);
+
+            let file = reader.read_zipfile(second, &bravo).await.unwrap();
+            assert_eq!(&*file, b"bravo");
+            let file = reader.read_zipfile(0, &alpha).await.unwrap();
+            assert_eq!(&*file, b"alpha");
+            assert!(matches!(
+                reader.read_zipfile(1, &alpha).await,
+                Err(ZipError::SignatureNotFound(_))
+            ));
+        })
+    }
+
+    #[test]
+    fn stream_entries_with_data_descriptors() {
+        future::block_on(async {
+            let archive = build_zip64_descriptor_archive("a.txt", b"alpha");
+            let mut zip = ZipArchive::new(Cursor::new(archive)).await.unwrap();
+            for read_ahead in [DEFAULT_READ_AHEAD, 0] {
+                zip.set_read_ahead(read_ahead);
+                let streamed: Vec<_> = zip.stream().map(Result::unwrap).collect().await;
+                assert_eq!(streamed.len(), 1);
+                assert_eq!(&*streamed[0], b"alpha");
+            }
+        })
+    }
+}
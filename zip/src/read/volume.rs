@@ -0,0 +1,288 @@
+use {
+    crate::error::{ZipError, ZipResult},
+    futures_lite::{
+        io::{AsyncRead, AsyncSeek, AsyncSeekExt, SeekFrom},
+        ready,
+    },
+    std::{
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+    },
+};
+
+pub struct MultiVolumeReader<R> {
+    volumes: Vec<R>,
+    starts: Box<[u64]>,
+    length: u64,
+    position: u64,
+    current: Option<usize>,
+}
+
+impl<R> MultiVolumeReader<R>
+where
+    R: AsyncSeek + Unpin,
+{
+    pub async fn new(mut volumes: Vec<R>) -> io::Result<Self> {
+        let mut starts = Vec::with_capacity(volumes.len());
+        let mut length = 0;
+
+        for volume in volumes.iter_mut() {
+            starts.push(length);
+            length += volume.seek(SeekFrom::End(0)).await?;
+        }
+
+        Ok(Self {
+            volumes,
+            starts: starts.into_boxed_slice(),
+            length,
+            position: 0,
+            current: None,
+        })
+    }
+}
+
+impl<R> MultiVolumeReader<R> {
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn volume_count(&self) -> usize {
+        self.volumes.len()
+    }
+
+    pub fn volume_starts(&self) -> &[u64] {
+        &self.starts
+    }
+
+    pub fn into_inner(self) -> Vec<R> {
+        self.volumes
+    }
+
+    fn volume_at(&self, position: u64) -> usize {
+        self.starts
+            .partition_point(|start| *start <= position)
+            .saturating_sub(1)
+    }
+}
+
+impl<R> AsyncRead for MultiVolumeReader<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.position >= this.length || buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let idx = this.volume_at(this.position);
+        let start = this.starts[idx];
+        if this.current != Some(idx) {
+            let local = this.position - start;
+            ready!(Pin::new(&mut this.volumes[idx]).poll_seek(cx, SeekFrom::Start(local)))?;
+            this.current = Some(idx);
+        }
+
+        let end = this.starts.get(idx + 1).copied().unwrap_or(this.length);
+        let max = (end - this.position).min(buf.len() as u64) as usize;
+        let read = ready!(Pin::new(&mut this.volumes[idx]).poll_read(cx, &mut buf[..max]))?;
+        this.position += read as u64;
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl<R> AsyncSeek for MultiVolumeReader<R>
+where
+    R: AsyncSeek + Unpin,
+{
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => this.length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => this.position.checked_add_signed(offset),
+        };
+
+        match position {
+            Some(position) => {
+                if position != this.position {
+                    this.position = position;
+                    this.current = None;
+                }
+                Poll::Ready(Ok(position))
+            }
+            None => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            ))),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Volumes {
+    starts: Box<[u64]>,
+    correction: u64,
+}
+
+impl Default for Volumes {
+    fn default() -> Self {
+        Self {
+            starts: Box::new([0]),
+            correction: 0,
+        }
+    }
+}
+
+impl Volumes {
+    pub(crate) fn new<S>(starts: S) -> Self
+    where
+        S: Into<Box<[u64]>>,
+    {
+        Self {
+            starts: starts.into(),
+            correction: 0,
+        }
+    }
+
+    pub(crate) fn correct(&mut self, correction: u64) {
+        self.correction = correction;
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    pub(crate) fn resolve(&self, disk: u32, offset: u64) -> ZipResult<u64> {
+        // Prepended data only shifts offsets within the first volume.
+        let correction = if disk == 0 { self.correction } else { 0 };
+        match self.starts.get(disk as usize) {
+            Some(start) => Ok(start + correction + offset),
+            None => Err(ZipError::InvalidArchive(
+                format!("Volume {} is missing", disk + 1).into(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            testing::{build_archive, build_volumes},
+            ZipArchive,
+        },
+        smol::{io::Cursor, stream::StreamExt},
+    };
+
+    #[test]
+    fn read_spanned_archive() {
+        smol::block_on(async {
+            let files: [(&str, &[u8]); 3] = [
+                ("first.txt", b"first volume"),
+                ("second.txt", b"second volume"),
+                ("third.txt", b"still the second volume"),
+            ];
+            let volumes = build_volumes(&files, Some(1))
+                .into_iter()
+                .map(Cursor::new)
+                .collect();
+
+            let mut zip = ZipArchive::with_volumes(volumes).await.unwrap();
+            assert_eq!(zip.volume_count(), 2);
+            for (name, data) in files {
+                assert_eq!(&*zip.file_by_name(name).await.unwrap(), data);
+            }
+        })
+    }
+
+    #[test]
+    fn read_spanned_zip64_archive() {
+        smol::block_on(async {
+            let files: [(&str, &[u8]); 2] = [("a.txt", b"alpha"), ("b.txt", b"bravo")];
+            let mut volumes = build_volumes(&files, Some(1));
+            let last = volumes.last_mut().unwrap();
+            let eocd = last.split_off(last.len() - 22);
+            let size = u32::from_le_bytes(eocd[12..16].try_into().unwrap()) as u64;
+            let offset = u32::from_le_bytes(eocd[16..20].try_into().unwrap()) as u64;
+            let record = last.len() as u64;
+
+            last.extend_from_slice(&0x06064b50u32.to_le_bytes());
+            last.extend_from_slice(&44u64.to_le_bytes());
+            last.extend_from_slice(&[45, 3, 45, 0]);
+            last.extend_from_slice(&1u32.to_le_bytes());
+            last.extend_from_slice(&1u32.to_le_bytes());
+            last.extend_from_slice(&1u64.to_le_bytes());
+            last.extend_from_slice(&2u64.to_le_bytes());
+            last.extend_from_slice(&size.to_le_bytes());
+            last.extend_from_slice(&offset.to_le_bytes());
+            // Keeps the record away from the locator, so only its stored
+            // disk and offset lead to it.
+            last.extend_from_slice(&[0; 8]);
+
+            last.extend_from_slice(&0x07064b50u32.to_le_bytes());
+            last.extend_from_slice(&1u32.to_le_bytes());
+            last.extend_from_slice(&record.to_le_bytes());
+            last.extend_from_slice(&2u32.to_le_bytes());
+
+            last.extend_from_slice(&0x06054b50u32.to_le_bytes());
+            last.extend_from_slice(&[1, 0, 1, 0]);
+            last.extend_from_slice(&[0xff; 12]);
+            last.extend_from_slice(&u16::MAX.to_le_bytes());
+            last.resize(last.len() + u16::MAX as usize, b'#');
+
+            let volumes = volumes.into_iter().map(Cursor::new).collect();
+            let mut zip = ZipArchive::with_volumes(volumes).await.unwrap();
+            assert_eq!(zip.volume_count(), 2);
+            for (name, data) in files {
+                assert_eq!(&*zip.file_by_name(name).await.unwrap(), data);
+            }
+        })
+    }
+
+    #[test]
+    fn read_split_archive() {
+        smol::block_on(async {
+            let files: [(&str, &[u8]); 2] = [("a.txt", b"aaaaaaaaaa"), ("b.txt", b"bbbbbbbbbb")];
+            let archive = build_archive(&files);
+            let volumes = archive
+                .chunks(17)
+                .map(|chunk| Cursor::new(chunk.to_vec()))
+                .collect();
+
+            let mut zip = ZipArchive::with_volumes(volumes).await.unwrap();
+            assert_eq!(zip.volume_count(), 1);
+            let mut stream = zip.stream();
+            for (name, data) in files {
+                let file = stream.next().await.unwrap().unwrap();
+                assert_eq!(file.file_name, name.into());
+                assert_eq!(&*file, data);
+            }
+        })
+    }
+
+    #[test]
+    fn reject_missing_volumes() {
+        smol::block_on(async {
+            let files: [(&str, &[u8]); 2] = [("a.txt", b"a"), ("b.txt", b"b")];
+            let volumes = build_volumes(&files, Some(1));
+            let last = Cursor::new(volumes[1].clone());
+
+            assert!(ZipArchive::new(last.clone()).await.is_err());
+            assert!(ZipArchive::with_volumes(vec![last]).await.is_err());
+        })
+    }
+}
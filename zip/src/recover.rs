@@ -0,0 +1,397 @@
+use {
+    crate::{
+        error::{ZipError, ZipResult},
+        index::DuplicatePolicy,
+        lookup::Lookup,
+        path::ZipPath,
+        read::{nested::DEFAULT_MAX_DEPTH, sequential::DEFAULT_READ_AHEAD, volume::Volumes},
+        specs::{
+            attribute::{AttributeCompatibility, Attributes},
+            extra_field::{zip64_block, HeaderId, Zip64ExtendedInfoExtraField},
+            DataDescriptor, LocalFileHeader, ZipEntry, ZipSpecs, DATA_DESCRIPTOR_LENGTH,
+            DATA_DESCRIPTOR_SIGNATURE, SIGNATURE_LENGTH, ZIP64_DATA_DESCRIPTOR_LENGTH,
+        },
+        write::ZipWriter,
+        ZipArchive, ZipFile,
+    },
+    futures_lite::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, SeekFrom},
+};
+
+const HEADER_SIZE: usize = SIGNATURE_LENGTH as usize + ZipFile::SIZE;
+/// Bytes read at a time while scanning the archive for signatures.
+const WINDOW: usize = 64 * 1024;
+
+impl<R> ZipArchive<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    /// Rebuilds the index from the local headers, for archives whose central
+    /// directory is missing or damaged.
+    pub async fn recover(mut reader: R) -> ZipResult<Self> {
+        let mut entries = Vec::new();
+        let mut end = 0;
+        let mut scanner = Scanner::new(&[ZipFile::SIGNATURE], 0);
+        while let Some((start, _)) = scanner.next(&mut reader).await? {
+            if let Some((entry, entry_end)) = recover_entry(&mut reader, start).await? {
+                entries.push(entry);
+                end = entry_end;
+                scanner.seek(entry_end);
+            }
+        }
+
+        let prefix_len = match entries.first() {
+            Some(entry) => entry.file_header_offset,
+            None => Err(ZipError::SignatureNotFound(
+                "No recoverable Local File Header found".into(),
+            ))?,
+        };
+
+        Ok(Self {
+            comment: None,
+            entries: entries.into_iter().collect(),
+            reader,
+            volumes: Volumes::default(),
+            central_directory_offset: end,
+            prefix_len,
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            duplicates: DuplicatePolicy::default(),
+            lookup: Lookup::default(),
+            read_ahead: DEFAULT_READ_AHEAD,
+        })
+    }
+
+    pub async fn write_repaired<W>(&mut self, writer: W) -> ZipResult<W>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut zip = ZipWriter::new(writer);
+        if let Some(comment) = &self.comment {
+            zip.set_comment(comment.as_str());
+        }
+
+        for index in 0..self.entries.len() {
+            let (entry, data) = self.read_raw(index).await?;
+            zip.copy_raw(&entry, &data).await?;
+        }
+        zip.finish().await
+    }
+}
+
+async fn recover_entry<R>(reader: &mut R, start: u64) -> ZipResult<Option<(ZipEntry, u64)>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let Some(fixed) = read_at(reader, start + SIGNATURE_LENGTH as u64, ZipFile::SIZE).await? else {
+        return Ok(None);
+    };
+    let Ok(header) = LocalFileHeader::try_from(<[u8; ZipFile::SIZE]>::try_from(&fixed[..])?) else {
+        return Ok(None);
+    };
+
+    let name_start = start + HEADER_SIZE as u64;
+    let Some(name) = read_at(reader, name_start, header.file_name_length as usize).await? else {
+        return Ok(None);
+    };
+    if name.is_empty() {
+        return Ok(None);
+    }
+    let extra_start = name_start + header.file_name_length as u64;
+    let Some(extra) = read_at(reader, extra_start, header.extra_field_length as usize).await?
+    else {
+        return Ok(None);
+    };
+    let zip64 = match zip64_block(&extra) {
+        Some(block) => Zip64ExtendedInfoExtraField::from_bytes(
+            HeaderId::ZIP64_EXTENDED_INFO_EXTRA_FIELD,
+            &block[4..],
+            header.uncompressed_size,
+            header.compressed_size,
+        )?,
+        None => Zip64ExtendedInfoExtraField::new(),
+    };
+    let data_start = start + header.length;
+
+    let (descriptor, end) = if header.flags.data_drescriptor {
+        let lengths = match zip64_block(&extra) {
+            Some(_) => [ZIP64_DATA_DESCRIPTOR_LENGTH, DATA_DESCRIPTOR_LENGTH],
+            None => [DATA_DESCRIPTOR_LENGTH, ZIP64_DATA_DESCRIPTOR_LENGTH],
+        };
+        match find_descriptor(reader, data_start, lengths).await? {
+            Some(found) => found,
+            None => return Ok(None),
+        }
+    } else {
+        let descriptor = DataDescriptor {
+            crc32: header.crc32,
+            compressed_size: zip64
+                .compressed_size
+                .unwrap_or(header.compressed_size as u64),
+            uncompressed_size: zip64
+                .uncompressed_size
+                .unwrap_or(header.uncompressed_size as u64),
+        };
+        let end = data_start + descriptor.compressed_size;
+        if end > reader.seek(SeekFrom::End(0)).await? {
+            return Ok(None);
+        }
+        (descriptor, end)
+    };
+
+    let directory = name.ends_with(b"/");
+    let external_attribute = Attributes::from((
+        if directory { 0x10 } else { 0 },
+        &AttributeCompatibility::MsDos,
+    ));
+    let mut file_name = ZipPath::from_bytes(&name);
+    file_name.update(&external_attribute);
+
+    let entry = ZipEntry {
+        version_made_by: AttributeCompatibility::MsDos,
+        version_needed: header.version_needed,
+        flags: header.flags,
+        compression: header.compression,
+        last_mod_datetime: header.last_mod_datetime,
+        crc32: descriptor.crc32,
+        compressed_size: descriptor.compressed_size,
+        uncompressed_size: descriptor.uncompressed_size,
+        disk_start: 0,
+        internal_attribute: 0,
+        external_attribute,
+        file_header_offset: start,
+        comment: None,
+        extra_field: None,
+        file_name,
+    };
+    Ok(Some((entry, end)))
+}
+
+/// The first data descriptor after `data_start` whose compressed size matches
+/// its distance from `data_start`, either signed or right before the next
+/// header, with the position where it ends. Each candidate is tried with the
+/// descriptor `lengths` in order, as zip64 descriptors hold 8-byte sizes.
+async fn find_descriptor<R>(
+    reader: &mut R,
+    data_start: u64,
+    lengths: [u8; 2],
+) -> ZipResult<Option<(DataDescriptor, u64)>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    const SIGNATURES: &[u32] = &[
+        DATA_DESCRIPTOR_SIGNATURE,
+        ZipFile::SIGNATURE,
+        ZipEntry::SIGNATURE,
+    ];
+
+    let mut scanner = Scanner::new(SIGNATURES, data_start);
+    while let Some((at, signature)) = scanner.next(reader).await? {
+        for length in lengths.map(u64::from) {
+            let (size, end) = match signature {
+                DATA_DESCRIPTOR_SIGNATURE => {
+                    (at - data_start, at + SIGNATURE_LENGTH as u64 + length)
+                }
+                _ => match (at - data_start).checked_sub(length) {
+                    Some(size) => (size, at),
+                    None => continue,
+                },
+            };
+            let Some(bytes) = read_at(reader, end - length, length as usize).await? else {
+                continue;
+            };
+            let descriptor = match <[u8; 20]>::try_from(&bytes[..]) {
+                Ok(zip64) => DataDescriptor::try_from(zip64)?,
+                Err(_) => DataDescriptor::try_from(<[u8; 12]>::try_from(&bytes[..])?)?,
+            };
+            if descriptor.compressed_size == size {
+                return Ok(Some((descriptor, end)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// `len` bytes at `position`, or `None` past the end of the archive.
+async fn read_at<R>(reader: &mut R, position: u64, len: usize) -> ZipResult<Option<Vec<u8>>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let mut buffer = Vec::with_capacity(len);
+    reader.seek(SeekFrom::Start(position)).await?;
+    reader.take(len as u64).read_to_end(&mut buffer).await?;
+    Ok((buffer.len() == len).then_some(buffer))
+}
+
+/// Finds signatures in order, reading the archive one window at a time.
+///
+/// Used instead of `FastSearch::search_all`, which needs the whole archive
+/// in memory: a damaged archive can be any size, and the scan resumes after
+/// each recovered entry rather than visiting every match.
+struct Scanner {
+    signatures: &'static [u32],
+    window: Vec<u8>,
+    /// Archive position of the start of `window`.
+    offset: u64,
+    /// Where in `window` to resume.
+    cursor: usize,
+}
+
+impl Scanner {
+    fn new(signatures: &'static [u32], position: u64) -> Self {
+        Self {
+            signatures,
+            window: Vec::new(),
+            offset: position,
+            cursor: 0,
+        }
+    }
+
+    /// Resumes the scan at `position`.
+    fn seek(&mut self, position: u64) {
+        match position.checked_sub(self.offset) {
+            Some(cursor) if cursor <= self.window.len() as u64 => self.cursor = cursor as usize,
+            _ => {
+                self.window.clear();
+                self.offset = position;
+                self.cursor = 0;
+            }
+        }
+    }
+
+    /// Position and value of the next signature.
+    async fn next<R>(&mut self, reader: &mut R) -> ZipResult<Option<(u64, u32)>>
+    where
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        let length = SIGNATURE_LENGTH as usize;
+        loop {
+            let hit = self.window[self.cursor..]
+                .windows(length)
+                .position(|bytes| {
+                    let signature = u32::from_le_bytes(bytes.try_into().expect("a signature"));
+                    self.signatures.contains(&signature)
+                });
+            if let Some(idx) = hit {
+                let at = self.cursor + idx;
+                let signature = u32::from_le_bytes(self.window[at..at + length].try_into()?);
+                self.cursor = at + 1;
+                return Ok(Some((self.offset + at as u64, signature)));
+            }
+
+            // The last bytes may start a signature completed by the next window.
+            let consumed = self
+                .window
+                .len()
+                .saturating_sub(length - 1)
+                .max(self.cursor);
+            self.offset += consumed as u64;
+            self.cursor = 0;
+            self.window.clear();
+            reader.seek(SeekFrom::Start(self.offset)).await?;
+            (&mut *reader)
+                .take(WINDOW as u64)
+                .read_to_end(&mut self.window)
+                .await?;
+            if self.window.len() < length {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            testing::{
+                build_archive, build_zip64_descriptor_archive, record_offset, streamed_entry,
+                zip64_entry,
+            },
+            ZipArchive,
+        },
+        smol::io::Cursor,
+    };
+
+    #[test]
+    fn recover_truncated_archive() {
+        smol::block_on(async {
+            let files: [(&str, &[u8]); 3] =
+                [("a.txt", b"alpha"), ("b/", b""), ("b/c.txt", b"charlie")];
+            let mut archive = build_archive(&files);
+
+            let central_directory = record_offset(&archive, 0);
+            archive.truncate(central_directory + 10);
+            archive.splice(
+                central_directory..central_directory,
+                streamed_entry("d.txt", b"delta"),
+            );
+            assert!(ZipArchive::new(Cursor::new(archive.clone())).await.is_err());
+
+            let mut zip = ZipArchive::recover(Cursor::new(archive)).await.unwrap();
+            assert_eq!(zip.len(), 4);
+            assert!(zip.entry_by_name("b/").unwrap().is_dir());
+            assert_eq!(&*zip.file_by_name("b/c.txt").await.unwrap(), b"charlie");
+            assert_eq!(zip.entry_by_name("d.txt").unwrap().uncompressed_size(), 5);
+            assert_eq!(&*zip.file_by_name("d.txt").await.unwrap(), b"delta");
+
+            let repaired = zip
+                .write_repaired(Cursor::new(Vec::new()))
+                .await
+                .unwrap()
+                .into_inner();
+            let mut zip = ZipArchive::new(Cursor::new(repaired)).await.unwrap();
+            assert_eq!(zip.len(), 4);
+            assert_eq!(&*zip.file_by_name("d.txt").await.unwrap(), b"delta");
+            assert!(zip.verify().await.is_ok());
+        })
+    }
+
+    #[test]
+    fn recover_entries_across_scan_windows() {
+        smol::block_on(async {
+            let mut data: Vec<u8> = (0..200_000u32).map(|idx| (idx * 7 % 251) as u8).collect();
+            // Signatures inside the data that do not end the entry.
+            data[70_000..70_004].copy_from_slice(&0x08074b50u32.to_le_bytes());
+            data[70_004..70_008].copy_from_slice(&9u32.to_le_bytes());
+            data[131_070..131_074].copy_from_slice(&0x04034b50u32.to_le_bytes());
+
+            let mut archive = b"stub".to_vec();
+            archive.extend(streamed_entry("big.bin", &data));
+            archive.extend(build_archive(&[("small.txt", b"small")]));
+            archive.truncate(archive.len() - 22);
+
+            let mut zip = ZipArchive::recover(Cursor::new(archive)).await.unwrap();
+            assert_eq!(zip.len(), 2);
+            assert_eq!(zip.prefix_len(), 4);
+            assert_eq!(
+                zip.entry_by_name("big.bin").unwrap().compressed_size(),
+                200_000
+            );
+
+            let repaired = zip
+                .write_repaired(Cursor::new(Vec::new()))
+                .await
+                .unwrap()
+                .into_inner();
+            let mut zip = ZipArchive::new(Cursor::new(repaired)).await.unwrap();
+            assert_eq!(&*zip.file_by_name("big.bin").await.unwrap(), data);
+            assert_eq!(&*zip.file_by_name("small.txt").await.unwrap(), b"small");
+        })
+    }
+    #[test]
+    fn recover_zip64_entries() {
+        smol::block_on(async {
+            let mut archive = build_zip64_descriptor_archive("a.txt", b"alpha");
+            archive.truncate(record_offset(&archive, 0));
+            archive.extend(zip64_entry("b.txt", b"bravo"));
+
+            let mut zip = ZipArchive::recover(Cursor::new(archive)).await.unwrap();
+            assert_eq!(zip.len(), 2);
+            assert_eq!(zip.entry_by_name("a.txt").unwrap().compressed_size(), 5);
+            assert_eq!(zip.entry_by_name("b.txt").unwrap().uncompressed_size(), 5);
+            assert_eq!(&*zip.file_by_name("a.txt").await.unwrap(), b"alpha");
+            assert_eq!(&*zip.file_by_name("b.txt").await.unwrap(), b"bravo");
+            assert!(zip.verify().await.is_ok());
+        })
+    }
+}
@@ -0,0 +1,206 @@
+use {
+    crate::{
+        error::{ZipError, ZipResult},
+        read::ZipAsyncReadExt,
+        specs::ZipEntry,
+        ZipArchive, ZipFile,
+    },
+    futures_lite::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, SeekFrom},
+    std::{
+        ffi::OsStr,
+        future::Future,
+        io,
+        ops::Deref,
+        pin::Pin,
+        sync::{Arc, Mutex},
+    },
+};
+
+type OpenFuture<R> = Pin<Box<dyn Future<Output = io::Result<R>> + Send>>;
+type Open<R> = Box<dyn Fn() -> OpenFuture<R> + Send + Sync>;
+
+struct ReaderPool<R> {
+    idle: Mutex<Vec<R>>,
+    open: Open<R>,
+}
+
+pub struct SharedZipArchive<R> {
+    archive: Arc<ZipArchive<()>>,
+    readers: Arc<ReaderPool<R>>,
+}
+
+impl<R> Clone for SharedZipArchive<R> {
+    fn clone(&self) -> Self {
+        Self {
+            archive: self.archive.clone(),
+            readers: self.readers.clone(),
+        }
+    }
+}
+
+impl<R> Deref for SharedZipArchive<R> {
+    type Target = ZipArchive<()>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.archive
+    }
+}
+
+impl<R> ReaderPool<R> {
+    async fn take(&self) -> io::Result<R> {
+        let idle = self.idle.lock().ok().and_then(|mut idle| idle.pop());
+        match idle {
+            Some(reader) => Ok(reader),
+            None => (self.open)().await,
+        }
+    }
+
+    fn give(&self, reader: R) {
+        if let Ok(mut idle) = self.idle.lock() {
+            idle.push(reader)
+        }
+    }
+}
+
+impl<R> SharedZipArchive<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    pub async fn file_by_name<S>(&self, path: S) -> ZipResult<ZipFile>
+    where
+        S: AsRef<OsStr>,
+    {
+        let entry = self
+            .archive
+            .index_of(path.as_ref())
+            .and_then(|index| self.archive.entries.get_index(index));
+        match entry {
+            Some(entry) => self.read(&entry).await,
+            None => Err(ZipError::InvalidArchive("Invalid Key".into())),
+        }
+    }
+
+    pub async fn file_by_index(&self, index: usize) -> ZipResult<ZipFile> {
+        match self.archive.entries.get_index(index) {
+            Some(entry) => self.read(&entry).await,
+            None => Err(ZipError::InvalidArchive("Invalid Index".into())),
+        }
+    }
+
+    async fn read(&self, entry: &ZipEntry) -> ZipResult<ZipFile> {
+        let offset = self
+            .archive
+            .volumes
+            .resolve(entry.disk_start as u32, entry.file_header_offset)?;
+
+        let mut reader = self.readers.take().await?;
+        reader.seek(SeekFrom::Start(offset)).await?;
+        let header = reader.read_local_header().await?;
+        let mut data = Vec::with_capacity(entry.compressed_size as usize);
+        (&mut reader)
+            .take(entry.compressed_size)
+            .read_to_end(&mut data)
+            .await?;
+        self.readers.give(reader);
+
+        let mut file = header.into_zipfile(data);
+        file.sizes_from(entry);
+        file.file_name.metadata = entry.file_name.metadata.clone();
+        Ok(file)
+    }
+}
+
+impl<R> ZipArchive<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    pub fn into_shared_with<F, Fut>(self, open: F) -> SharedZipArchive<R>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<R>> + Send + 'static,
+    {
+        let (archive, reader) = self.split();
+        let readers = ReaderPool {
+            idle: Mutex::new(vec![reader]),
+            open: Box::new(move || Box::pin(open())),
+        };
+
+        SharedZipArchive {
+            archive: Arc::new(archive),
+            readers: Arc::new(readers),
+        }
+    }
+
+    pub fn into_shared(self) -> SharedZipArchive<R>
+    where
+        R: Clone + Send + Sync + 'static,
+    {
+        let template = self.reader.clone();
+        self.into_shared_with(move || {
+            let reader = template.clone();
+            async move { Ok(reader) }
+        })
+    }
+
+    fn split(self) -> (ZipArchive<()>, R) {
+        let archive = ZipArchive {
+            comment: self.comment,
+            entries: self.entries,
+            reader: (),
+            volumes: self.volumes,
+            central_directory_offset: self.central_directory_offset,
+            prefix_len: self.prefix_len,
+            depth: self.depth,
+            max_depth: self.max_depth,
+            duplicates: self.duplicates,
+            lookup: self.lookup,
+            read_ahead: self.read_ahead,
+        };
+        (archive, self.reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{testing::build_archive, ZipArchive},
+        smol::io::Cursor,
+    };
+
+    #[test]
+    fn share_archive_between_tasks() {
+        fn assert_send<T: Send>(_: &T) {}
+
+        smol::block_on(async {
+            let files: Vec<(String, Vec<u8>)> = (0..8)
+                .map(|idx| (format!("file-{idx}.txt"), vec![idx as u8; 32 * idx]))
+                .collect();
+            let borrowed: Vec<(&str, &[u8])> = files
+                .iter()
+                .map(|(name, data)| (name.as_str(), data.as_slice()))
+                .collect();
+            let mut zip = ZipArchive::new(Cursor::new(build_archive(&borrowed)))
+                .await
+                .unwrap();
+            assert_send(&zip.stream());
+
+            let shared = zip.into_shared();
+            let tasks: Vec<_> = files
+                .iter()
+                .cloned()
+                .map(|(name, data)| {
+                    let shared = shared.clone();
+                    smol::spawn(async move {
+                        let file = shared.file_by_name(&name).await.unwrap();
+                        assert_eq!(&*file, data.as_slice());
+                    })
+                })
+                .collect();
+            for task in tasks {
+                task.await;
+            }
+            assert_eq!(shared.len(), 8);
+            assert!(shared.entry_by_name("file-3.txt").is_some());
+        })
+    }
+}
@@ -1,16 +1,18 @@
-pub(crate) mod attribute;
+pub mod attribute;
 pub mod compression;
 pub mod extra_field;
 
 use {
-    crate::{datetime::ZipDateTime, ZipError, ZipPath, ZipResult},
+    crate::{datetime::ZipDateTime, ZipError, ZipFile, ZipPath},
     attribute::{AttributeCompatibility, Attributes},
     compression::Compression,
-    extra_field::ExtraField,
+    extra_field::{zip64_block, ExtraField, HeaderId, Zip64ExtendedInfoExtraField},
+    std::path::PathBuf,
 };
 
 pub(crate) const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x8074b50;
 pub(crate) const DATA_DESCRIPTOR_LENGTH: u8 = 12;
+pub(crate) const ZIP64_DATA_DESCRIPTOR_LENGTH: u8 = 20;
 pub(crate) const SIGNATURE_LENGTH: u8 = 4;
 
 pub(crate) trait ZipSpecs {
@@ -21,18 +23,38 @@ pub(crate) trait ZipSpecs {
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub(crate) struct DataDescriptor {
     pub crc32: u32,
-    pub compressed_size: u32,
-    pub uncompressed_size: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub(crate) struct GeneralPurposeFlag {
     pub(crate) encrypted: bool,
+    pub(crate) compression_options: u8,
     pub(crate) data_drescriptor: bool,
     pub(crate) utf8_required: bool,
     pub(crate) central_directory_encrypted: bool,
 }
 
+#[derive(Debug)]
+pub(crate) struct LocalFileHeader {
+    pub(crate) version_needed: u16,
+    pub(crate) flags: GeneralPurposeFlag,
+    pub(crate) compression: Compression,
+    pub(crate) last_mod_datetime: ZipDateTime,
+    pub(crate) crc32: u32,
+    pub(crate) compressed_size: u32,
+    pub(crate) uncompressed_size: u32,
+    pub(crate) file_name: ZipPath,
+    pub(crate) extra_field: Option<Vec<ExtraField>>,
+    pub(crate) file_name_length: u16,
+    pub(crate) extra_field_length: u16,
+    pub(crate) length: u64,
+    /// Whether the extra fields hold a zip64 block, making a data descriptor
+    /// use 8-byte sizes.
+    pub(crate) zip64: bool,
+}
+
 #[derive(Debug)]
 pub(crate) struct Zip64CentralDirectoryEndRecord {
     pub(crate) version_made_by: u16,
@@ -49,6 +71,7 @@ pub(crate) struct Zip64CentralDirectoryEndRecord {
 pub(crate) struct Zip64CentralDirectoryEndLocator {
     pub(crate) number_of_disk_with_zip64_central_directory_end: u32,
     pub(crate) relative_offset: u64,
+    #[allow(dead_code)]
     pub(crate) number_of_disks: u32,
 }
 
@@ -65,31 +88,36 @@ pub(crate) struct Zip32CentralDirectoryEndRecord {
 
 #[derive(Debug)]
 pub(crate) struct ZipCentralDirectoryEndRecord {
-    pub(crate) _version_made_by: Option<u16>,
+    #[allow(dead_code)]
+    pub(crate) version_made_by: Option<u16>,
+    #[allow(dead_code)]
     pub(crate) version_needed: Option<u16>,
     pub(crate) disk_number: u32,
     pub(crate) central_directory_start_disk: u32,
+    #[allow(dead_code)]
     pub(crate) number_of_entries_in_disk: u64,
+    #[allow(dead_code)]
     pub(crate) number_of_entries: u64,
     pub(crate) central_directory_size: u64,
     pub(crate) central_directory_offset: u64,
     pub(crate) comment: Option<String>,
+    pub(crate) offset: u64,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub(crate) struct ZipEntry {
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ZipEntry {
     pub(crate) version_made_by: AttributeCompatibility,
     pub(crate) version_needed: u16,
     pub(crate) flags: GeneralPurposeFlag,
     pub(crate) compression: Compression,
     pub(crate) last_mod_datetime: ZipDateTime,
     pub(crate) crc32: u32,
-    pub(crate) compressed_size: u32,
-    pub(crate) uncompressed_size: u32,
+    pub(crate) compressed_size: u64,
+    pub(crate) uncompressed_size: u64,
     pub(crate) disk_start: u16,
     pub(crate) internal_attribute: u16,
     pub(crate) external_attribute: Attributes,
-    pub(crate) file_header_offset: u32,
+    pub(crate) file_header_offset: u64,
     pub comment: Option<String>,
     pub extra_field: Option<Vec<ExtraField>>,
     pub file_name: ZipPath,
@@ -98,12 +126,14 @@ pub(crate) struct ZipEntry {
 impl From<u16> for GeneralPurposeFlag {
     fn from(value: u16) -> Self {
         let encrypted = matches!(value & 0x1, 1);
+        let compression_options = ((value & 0x6) >> 1) as u8;
         let data_drescriptor = matches!((value & 0x8) >> 3, 1);
         let utf8_required = matches!((value & 0x800) >> 11, 1);
         let central_directory_encrypted = matches!((value & 0x2000) >> 13, 1);
 
         Self {
             encrypted,
+            compression_options,
             data_drescriptor,
             utf8_required,
             central_directory_encrypted,
@@ -111,50 +141,188 @@ impl From<u16> for GeneralPurposeFlag {
     }
 }
 
+impl From<&GeneralPurposeFlag> for u16 {
+    fn from(value: &GeneralPurposeFlag) -> Self {
+        (value.encrypted as u16)
+            | (value.compression_options as u16 & 0x3) << 1
+            | (value.data_drescriptor as u16) << 3
+            | (value.utf8_required as u16) << 11
+            | (value.central_directory_encrypted as u16) << 13
+    }
+}
+
+impl LocalFileHeader {
+    pub(crate) fn into_zipfile(self, data: Vec<u8>) -> ZipFile {
+        ZipFile {
+            version_needed: self.version_needed,
+            flags: self.flags,
+            compression: self.compression,
+            last_mod_datetime: self.last_mod_datetime,
+            crc32: self.crc32,
+            compressed_size: self.compressed_size as u64,
+            uncompressed_size: self.uncompressed_size as u64,
+            file_name: self.file_name,
+            extra_field: self.extra_field,
+            data,
+        }
+    }
+}
+
+impl ZipFile {
+    /// Takes the checksum and sizes from the central directory, as the local
+    /// header leaves them to a data descriptor or, for zip64 entries, to
+    /// placeholders.
+    pub(crate) fn sizes_from(&mut self, entry: &ZipEntry) {
+        self.crc32 = entry.crc32;
+        self.compressed_size = entry.compressed_size;
+        self.uncompressed_size = entry.uncompressed_size;
+    }
+}
+
+impl ZipEntry {
+    pub fn name(&self) -> &ZipPath {
+        &self.file_name
+    }
+
+    /// The name exactly as stored in the central directory.
+    pub fn raw_name(&self) -> &[u8] {
+        self.file_name.raw_name()
+    }
+
+    /// Where this entry is extracted to, relative to the destination.
+    pub fn sanitized_path(&self) -> PathBuf {
+        self.file_name.sanitized().0
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    pub fn last_modified(&self) -> ZipDateTime {
+        self.last_mod_datetime
+    }
+
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
+    pub fn compressed_size(&self) -> u64 {
+        self.compressed_size
+    }
+
+    pub fn uncompressed_size(&self) -> u64 {
+        self.uncompressed_size
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    pub fn attributes(&self) -> &Attributes {
+        &self.external_attribute
+    }
+
+    pub fn version_needed(&self) -> u16 {
+        self.version_needed
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.flags.encrypted
+    }
+
+    pub fn has_data_descriptor(&self) -> bool {
+        self.flags.data_drescriptor
+    }
+
+    pub fn disk_start(&self) -> u16 {
+        self.disk_start
+    }
+
+    pub fn header_offset(&self) -> u64 {
+        self.file_header_offset
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.external_attribute.directory
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.external_attribute.file
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.external_attribute.symbolic
+    }
+}
+
 impl<'a> TryFrom<&'a [u8]> for ZipEntry {
     type Error = ZipError;
 
     fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
+        let filename_length = u16::from_le_bytes(value[28..30].try_into()?) as usize;
+        let extra_field_length = u16::from_le_bytes(value[30..32].try_into()?) as usize;
+        let comment_length = u16::from_le_bytes(value[32..34].try_into()?) as usize;
+        let name = &value[46..46 + filename_length];
+        let start_idx = 46 + filename_length + extra_field_length;
+        let extra = &value[46 + filename_length..start_idx];
+        let comment = &value[start_idx..start_idx + comment_length];
+        Self::from_parts(&value[..46], name, extra, comment)
+    }
+}
+
+impl ZipEntry {
+    /// Builds an entry from the fixed part of a central directory header and its
+    /// variable-length name, extra fields and comment. Sizes and the header
+    /// offset saturated at `u32::MAX` are read from the zip64 block in `extra`.
+    pub(crate) fn from_parts(
+        value: &[u8],
+        name: &[u8],
+        extra: &[u8],
+        comment: &[u8],
+    ) -> Result<Self, ZipError> {
         let version_made_by = AttributeCompatibility::try_from(value[5])?;
         let version_needed = u16::from_le_bytes(value[6..8].try_into()?);
         let flags = GeneralPurposeFlag::from(u16::from_le_bytes(value[8..10].try_into()?));
-        let compression = Compression::try_from(u16::from_le_bytes(value[10..12].try_into()?))?;
+        let compression = Compression::from(u16::from_le_bytes(value[10..12].try_into()?));
         let last_mod_datetime =
             ZipDateTime::try_from(TryInto::<[u8; 4]>::try_into(&value[12..16])?)?;
         let crc32 = u32::from_le_bytes(value[16..20].try_into()?);
         let compressed_size = u32::from_le_bytes(value[20..24].try_into()?);
         let uncompressed_size = u32::from_le_bytes(value[24..28].try_into()?);
-        let filename_length = u16::from_le_bytes(value[28..30].try_into()?) as usize;
-        let extra_field_length = u16::from_le_bytes(value[30..32].try_into()?) as usize;
-        let comment_length = u16::from_le_bytes(value[32..34].try_into()?) as usize;
-        let file_name = {
-            let end_idx = 46 + filename_length;
-            let buffer = Vec::from(&value[46..end_idx]);
-            let string = String::from_utf8(buffer)
-                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
-            ZipPath::from(string)
-        };
+        let mut file_name = ZipPath::from_bytes(name);
         let extra_field: Option<Vec<ExtraField>> = None;
-        let comment = {
-            let start_idx = 46 + filename_length + extra_field_length;
-            let end_idx = start_idx + comment_length;
-            if comment_length > 0 {
-                let buffer = Vec::from(&value[start_idx..end_idx]);
-                Some(
-                    String::from_utf8(buffer)
-                        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?,
-                )
-            } else {
-                None
-            }
+        let comment = if !comment.is_empty() {
+            Some(String::from_utf8_lossy(comment).into_owned())
+        } else {
+            None
         };
         let disk_start = u16::from_le_bytes(value[34..36].try_into()?);
         let internal_attribute = u16::from_le_bytes(value[36..38].try_into()?);
-        let external_attribute = Attributes::try_from((
+        let mut external_attribute = Attributes::from((
             u32::from_le_bytes(value[38..42].try_into()?),
             &version_made_by,
-        ))?;
+        ));
+        if file_name.raw_name().ends_with(b"/") {
+            external_attribute.directory = true;
+            external_attribute.file = false;
+        }
+        file_name.update(&external_attribute);
         let file_header_offset = u32::from_le_bytes(value[42..46].try_into()?);
+        let zip64 = match zip64_block(extra) {
+            Some(block) => Zip64ExtendedInfoExtraField::from_bytes(
+                HeaderId::ZIP64_EXTENDED_INFO_EXTRA_FIELD,
+                &block[4..],
+                uncompressed_size,
+                compressed_size,
+            )?,
+            None => Zip64ExtendedInfoExtraField::new(),
+        };
+        let compressed_size = zip64.compressed_size.unwrap_or(compressed_size as u64);
+        let uncompressed_size = zip64.uncompressed_size.unwrap_or(uncompressed_size as u64);
+        let file_header_offset = match zip64.relative_header_offset {
+            Some(offset) if file_header_offset == u32::MAX => offset,
+            _ => file_header_offset as u64,
+        };
 
         Ok(ZipEntry {
             version_made_by,
@@ -176,6 +344,73 @@ impl<'a> TryFrom<&'a [u8]> for ZipEntry {
     }
 }
 
+impl TryFrom<[u8; 12]> for DataDescriptor {
+    type Error = ZipError;
+
+    fn try_from(value: [u8; 12]) -> Result<Self, Self::Error> {
+        let crc32 = u32::from_le_bytes(value[0..4].try_into()?);
+        let compressed_size = u32::from_le_bytes(value[4..8].try_into()?) as u64;
+        let uncompressed_size = u32::from_le_bytes(value[8..12].try_into()?) as u64;
+        Ok(Self {
+            crc32,
+            compressed_size,
+            uncompressed_size,
+        })
+    }
+}
+
+impl TryFrom<[u8; 20]> for DataDescriptor {
+    type Error = ZipError;
+
+    fn try_from(value: [u8; 20]) -> Result<Self, Self::Error> {
+        let crc32 = u32::from_le_bytes(value[0..4].try_into()?);
+        let compressed_size = u64::from_le_bytes(value[4..12].try_into()?);
+        let uncompressed_size = u64::from_le_bytes(value[12..20].try_into()?);
+        Ok(Self {
+            crc32,
+            compressed_size,
+            uncompressed_size,
+        })
+    }
+}
+
+impl TryFrom<[u8; 26]> for LocalFileHeader {
+    type Error = ZipError;
+
+    fn try_from(value: [u8; 26]) -> Result<Self, Self::Error> {
+        let datetime: [u8; 4] = value[6..10].try_into()?;
+
+        let version_needed = u16::from_le_bytes(value[0..2].try_into()?);
+        let flags = GeneralPurposeFlag::from(u16::from_le_bytes(value[2..4].try_into()?));
+        let compression = Compression::from(u16::from_le_bytes(value[4..6].try_into()?));
+        let last_mod_datetime = ZipDateTime::try_from(datetime)?;
+        let crc32 = u32::from_le_bytes(value[10..14].try_into()?);
+        let compressed_size = u32::from_le_bytes(value[14..18].try_into()?);
+        let uncompressed_size = u32::from_le_bytes(value[18..22].try_into()?);
+        let file_name_length = u16::from_le_bytes(value[22..24].try_into()?);
+        let extra_field_length = u16::from_le_bytes(value[24..26].try_into()?);
+        let length = (SIGNATURE_LENGTH as usize + ZipFile::SIZE) as u64
+            + file_name_length as u64
+            + extra_field_length as u64;
+
+        Ok(Self {
+            version_needed,
+            flags,
+            compression,
+            last_mod_datetime,
+            crc32,
+            compressed_size,
+            uncompressed_size,
+            file_name: ZipPath::new(),
+            extra_field: None,
+            file_name_length,
+            extra_field_length,
+            length,
+            zip64: false,
+        })
+    }
+}
+
 impl TryFrom<[u8; 16]> for Zip64CentralDirectoryEndLocator {
     type Error = ZipError;
 
@@ -239,3 +474,72 @@ impl TryFrom<[u8; 52]> for Zip64CentralDirectoryEndRecord {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            compression::Compression,
+            testing::{build_archive, build_zip64_descriptor_archive, zip64_entry},
+            ZipArchive,
+        },
+        smol::io::Cursor,
+    };
+
+    #[test]
+    fn list_entries_without_reading_data() {
+        smol::block_on(async {
+            let files: [(&str, &[u8]); 3] = [
+                ("docs/", b""),
+                ("docs/readme.txt", b"read me"),
+                ("image.bin", &[0; 64]),
+            ];
+            let zip = ZipArchive::new(Cursor::new(build_archive(&files)))
+                .await
+                .unwrap();
+
+            let listing: Vec<_> = zip
+                .entries()
+                .map(|entry| {
+                    (
+                        entry.name().clone(),
+                        entry.uncompressed_size(),
+                        entry.is_dir(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                listing,
+                [
+                    ("docs/".into(), 0, true),
+                    ("docs/readme.txt".into(), 7, false),
+                    ("image.bin".into(), 64, false),
+                ]
+            );
+
+            let entry = zip.entry_by_name("docs/readme.txt").unwrap();
+            assert_eq!(entry.compression(), Compression::Stored);
+            assert_eq!(entry.compressed_size(), 7);
+            assert!(entry.is_file());
+            assert!(entry.attributes().owner.read);
+            assert!(zip.entry_by_name("missing.txt").is_none());
+            assert_eq!(zip.entry_by_index(2).unwrap().name(), &"image.bin".into());
+        })
+    }
+    #[test]
+    fn take_file_sizes_from_the_central_directory() {
+        smol::block_on(async {
+            let archive = build_zip64_descriptor_archive("a.txt", b"alpha");
+            let mut zip = ZipArchive::new(Cursor::new(archive)).await.unwrap();
+            let file = zip.file_by_name("a.txt").await.unwrap();
+            assert_eq!(file.crc32, crc32fast::hash(b"alpha"));
+            assert_eq!((file.compressed_size, file.uncompressed_size), (5, 5));
+
+            let archive = zip64_entry("b.txt", b"bravo");
+            let mut zip = ZipArchive::recover(Cursor::new(archive)).await.unwrap();
+            let file = zip.file_by_name("b.txt").await.unwrap();
+            assert_eq!((file.compressed_size, file.uncompressed_size), (5, 5));
+            assert_eq!(file.extract().await.unwrap(), b"bravo");
+        })
+    }
+}
@@ -9,7 +9,7 @@ macro_rules! mask {
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub(crate) enum AttributeCompatibility {
     MsDos,
-    NTFS,
+    Ntfs,
     Unix,
 }
 
@@ -37,7 +37,7 @@ impl TryFrom<u8> for AttributeCompatibility {
         match value {
             0 => Ok(Self::MsDos),
             3 => Ok(Self::Unix),
-            10 => Ok(Self::NTFS),
+            10 => Ok(Self::Ntfs),
             _ => Err(ZipError::AttributeCompatibilityNotSupported),
         }
     }
@@ -50,9 +50,9 @@ impl<'a> From<(u32, &'a AttributeCompatibility)> for Attributes {
         match attribute {
             AttributeCompatibility::Unix => {
                 let value = value >> 16;
-                let directory = mask!(value, 0o040000);
-                let file = mask!(value, 0o100000);
-                let symbolic = mask!(value, 0o120000);
+                let directory = value & 0o170000 == 0o040000;
+                let file = value & 0o170000 == 0o100000;
+                let symbolic = value & 0o170000 == 0o120000;
 
                 let owner = {
                     let read = mask!(value, 0o400);
@@ -67,9 +67,9 @@ impl<'a> From<(u32, &'a AttributeCompatibility)> for Attributes {
                 };
 
                 let group = {
-                    let read = matches!(value & 0o040, 0o040);
-                    let write = matches!(value & 0o020, 0o020);
-                    let execute = matches!(value & 0o010, 0o010);
+                    let read = mask!(value, 0o040);
+                    let write = mask!(value, 0o020);
+                    let execute = mask!(value, 0o010);
 
                     Permissions {
                         read,
@@ -79,10 +79,9 @@ impl<'a> From<(u32, &'a AttributeCompatibility)> for Attributes {
                 };
 
                 let other = {
-                    let other_permissions = value & 0x007;
-                    let execute = matches!(other_permissions & 0x001, 1);
-                    let read = matches!(other_permissions & 0x004, 1);
-                    let write = matches!(other_permissions & 0x002, 1);
+                    let execute = mask!(value, 0o001);
+                    let read = mask!(value, 0o004);
+                    let write = mask!(value, 0o002);
 
                     Permissions {
                         execute,
@@ -99,17 +98,45 @@ impl<'a> From<(u32, &'a AttributeCompatibility)> for Attributes {
                     other,
                 }
             }
-            _ => Self::default(),
+            _ => {
+                let directory = mask!(value, 0x10);
+                Self {
+                    directory,
+                    file: !directory,
+                    ..Self::default()
+                }
+            }
         }
     }
 }
 
+impl From<&Attributes> for u32 {
+    fn from(value: &Attributes) -> Self {
+        let bits = |permissions: &Permissions| {
+            (permissions.read as u32) << 2
+                | (permissions.write as u32) << 1
+                | permissions.execute as u32
+        };
+        let kind = if value.symbolic {
+            0o120000
+        } else if value.directory {
+            0o040000
+        } else {
+            0o100000
+        };
+        let mode = kind | bits(&value.owner) << 6 | bits(&value.group) << 3 | bits(&value.other);
+        let dos = if value.directory { 0x10 } else { 0 };
+
+        mode << 16 | dos
+    }
+}
+
 impl From<AttributeCompatibility> for u16 {
     fn from(value: AttributeCompatibility) -> Self {
         match value {
             AttributeCompatibility::MsDos => 0,
             AttributeCompatibility::Unix => 3,
-            AttributeCompatibility::NTFS => 10,
+            AttributeCompatibility::Ntfs => 10,
         }
     }
 }
@@ -1,109 +1,164 @@
+#[cfg(feature = "legacy")]
+mod bits;
+mod codec;
+#[cfg(feature = "legacy")]
+mod implode;
+#[cfg(feature = "ppmd")]
+mod ppmd;
+#[cfg(feature = "legacy")]
+mod reduce;
+#[cfg(feature = "legacy")]
+mod shrink;
+
+pub(crate) use codec::registered;
+pub use codec::{register_codec, unregister_codec, Codec};
+
+#[cfg(feature = "deflate64")]
+use async_compression::futures::bufread::Deflate64Decoder;
+#[cfg(feature = "lzma")]
+use async_compression::futures::bufread::LzmaDecoder;
+#[cfg(feature = "bzip2")]
+use async_compression::futures::bufread::{BzDecoder, BzEncoder};
+#[cfg(feature = "deflate")]
+use async_compression::futures::bufread::{DeflateDecoder, DeflateEncoder};
+#[cfg(feature = "xz")]
+use async_compression::futures::bufread::{XzDecoder, XzEncoder};
+#[cfg(feature = "zstd")]
+use async_compression::futures::bufread::{ZstdDecoder, ZstdEncoder};
+#[cfg(any(
+    feature = "bzip2",
+    feature = "deflate",
+    feature = "xz",
+    feature = "zstd"
+))]
+use async_compression::Level;
 use {
     crate::error::{ZipError, ZipResult},
-    async_compression::futures::bufread::*,
-    smol::io::{AsyncBufRead, AsyncReadExt},
+    futures_lite::io::{AsyncRead, AsyncReadExt},
 };
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
 pub enum Compression {
     Stored,
+    Shrink,
+    Reduce(u8),
+    Implode,
     Deflate,
     Deflate64,
     Bzip2,
     Lzma,
     Zstd,
     Xz,
+    Ppmd,
+    Other(u16),
 }
 
-pub(crate) trait Decode<R> {
-    async fn decode(data: R, size: usize) -> ZipResult<Vec<u8>>;
-}
-
-impl<R> Decode<R> for ZstdDecoder<R>
-where
-    R: AsyncBufRead + Unpin,
-{
-    async fn decode(data: R, size: usize) -> ZipResult<Vec<u8>> {
-        let mut buffer = Vec::with_capacity(size);
-        let mut decoder = Self::new(data);
-        decoder.read_to_end(&mut buffer).await?;
-        Ok(buffer)
-    }
-}
-
-impl<R> Decode<R> for XzDecoder<R>
-where
-    R: AsyncBufRead + Unpin,
-{
-    async fn decode(data: R, size: usize) -> ZipResult<Vec<u8>> {
-        let mut buffer = Vec::with_capacity(size);
-        let mut decoder = Self::new(data);
-        decoder.read_to_end(&mut buffer).await?;
-        Ok(buffer)
-    }
-}
-
-impl<R> Decode<R> for LzmaDecoder<R>
-where
-    R: AsyncBufRead + Unpin,
-{
-    async fn decode(data: R, size: usize) -> ZipResult<Vec<u8>> {
-        let mut buffer = Vec::with_capacity(size);
-        let mut decoder = Self::new(data);
-        decoder.read_to_end(&mut buffer).await?;
-        Ok(buffer)
+impl Compression {
+    #[cfg_attr(not(feature = "legacy"), allow(unused_variables))]
+    pub(crate) async fn decompress(
+        self,
+        data: &[u8],
+        size: usize,
+        options: u8,
+    ) -> ZipResult<Vec<u8>> {
+        match self {
+            Self::Stored => read_to_end(data, size).await,
+            #[cfg(feature = "legacy")]
+            Self::Shrink => shrink::decompress(data, size),
+            #[cfg(feature = "legacy")]
+            Self::Reduce(factor) => reduce::decompress(data, size, factor),
+            #[cfg(feature = "legacy")]
+            Self::Implode => implode::decompress(data, size, options),
+            #[cfg(feature = "deflate")]
+            Self::Deflate => read_to_end(DeflateDecoder::new(data), size).await,
+            #[cfg(feature = "deflate64")]
+            Self::Deflate64 => read_to_end(Deflate64Decoder::new(data), size).await,
+            #[cfg(feature = "bzip2")]
+            Self::Bzip2 => read_to_end(BzDecoder::new(data), size).await,
+            #[cfg(feature = "lzma")]
+            Self::Lzma => read_to_end(LzmaDecoder::new(data), size).await,
+            #[cfg(feature = "zstd")]
+            Self::Zstd => read_to_end(ZstdDecoder::new(data), size).await,
+            #[cfg(feature = "xz")]
+            Self::Xz => read_to_end(XzDecoder::new(data), size).await,
+            #[cfg(feature = "ppmd")]
+            Self::Ppmd => ppmd::decompress(data, size),
+            Self::Other(method) => registered(method)?.decode(data, size),
+            #[allow(unreachable_patterns)]
+            method => Err(ZipError::CompressionDisabled(method)),
+        }
     }
-}
 
-impl<R> Decode<R> for BzDecoder<R>
-where
-    R: AsyncBufRead + Unpin,
-{
-    async fn decode(data: R, size: usize) -> ZipResult<Vec<u8>> {
-        let mut buffer = Vec::with_capacity(size);
-        let mut decoder = Self::new(data);
-        decoder.read_to_end(&mut buffer).await?;
-        Ok(buffer)
+    /// `level` is passed to the deflate, bzip2, zstd and xz encoders; other
+    /// methods ignore it.
+    #[cfg_attr(
+        not(any(
+            feature = "bzip2",
+            feature = "deflate",
+            feature = "xz",
+            feature = "zstd"
+        )),
+        allow(unused_variables)
+    )]
+    pub(crate) async fn compress(self, data: &[u8], level: Option<i32>) -> ZipResult<Vec<u8>> {
+        match self {
+            Self::Stored => read_to_end(data, data.len()).await,
+            #[cfg(feature = "deflate")]
+            Self::Deflate => {
+                read_to_end(DeflateEncoder::with_quality(data, quality(level)), 0).await
+            }
+            #[cfg(feature = "bzip2")]
+            Self::Bzip2 => read_to_end(BzEncoder::with_quality(data, quality(level)), 0).await,
+            #[cfg(feature = "zstd")]
+            Self::Zstd => read_to_end(ZstdEncoder::with_quality(data, quality(level)), 0).await,
+            #[cfg(feature = "xz")]
+            Self::Xz => read_to_end(XzEncoder::with_quality(data, quality(level)), 0).await,
+            #[cfg(feature = "ppmd")]
+            Self::Ppmd => ppmd::compress(data),
+            Self::Other(method) => registered(method)?.encode(data),
+            Self::Shrink | Self::Reduce(_) | Self::Implode | Self::Deflate64 | Self::Lzma => {
+                Err(ZipError::CompressionNotSupported)
+            }
+            #[allow(unreachable_patterns)]
+            method => Err(ZipError::CompressionDisabled(method)),
+        }
     }
 }
 
-impl<R> Decode<R> for Deflate64Decoder<R>
-where
-    R: AsyncBufRead + Unpin,
-{
-    async fn decode(data: R, size: usize) -> ZipResult<Vec<u8>> {
-        let mut buffer = Vec::with_capacity(size);
-        let mut decoder = Self::new(data);
-        decoder.read_to_end(&mut buffer).await?;
-        Ok(buffer)
-    }
+#[cfg(any(
+    feature = "bzip2",
+    feature = "deflate",
+    feature = "xz",
+    feature = "zstd"
+))]
+fn quality(level: Option<i32>) -> Level {
+    level.map_or(Level::Default, Level::Precise)
 }
 
-impl<R> Decode<R> for DeflateDecoder<R>
+async fn read_to_end<R>(mut reader: R, capacity: usize) -> ZipResult<Vec<u8>>
 where
-    R: AsyncBufRead + Unpin,
+    R: AsyncRead + Unpin,
 {
-    async fn decode(data: R, size: usize) -> ZipResult<Vec<u8>> {
-        let mut buffer = Vec::with_capacity(size);
-        let mut decoder = Self::new(data);
-        decoder.read_to_end(&mut buffer).await?;
-        Ok(buffer)
-    }
+    let mut buffer = Vec::with_capacity(capacity);
+    reader.read_to_end(&mut buffer).await?;
+    Ok(buffer)
 }
 
-impl TryFrom<u16> for Compression {
-    type Error = ZipError;
-
-    fn try_from(value: u16) -> Result<Self, Self::Error> {
+impl From<u16> for Compression {
+    fn from(value: u16) -> Self {
         match value {
-            0 => Ok(Self::Stored),
-            8 => Ok(Self::Deflate),
-            9 => Ok(Self::Deflate64),
-            12 => Ok(Self::Bzip2),
-            14 => Ok(Self::Lzma),
-            93 => Ok(Self::Zstd),
-            95 => Ok(Self::Xz),
-            _ => Err(ZipError::CompressionNotSupported),
+            0 => Self::Stored,
+            1 => Self::Shrink,
+            2..=5 => Self::Reduce(value as u8 - 1),
+            6 => Self::Implode,
+            8 => Self::Deflate,
+            9 => Self::Deflate64,
+            12 => Self::Bzip2,
+            14 => Self::Lzma,
+            93 => Self::Zstd,
+            95 => Self::Xz,
+            98 => Self::Ppmd,
+            method => Self::Other(method),
         }
     }
 }
@@ -112,12 +167,129 @@ impl From<Compression> for u16 {
     fn from(value: Compression) -> Self {
         match value {
             Compression::Stored => 0,
+            Compression::Shrink => 1,
+            Compression::Reduce(factor) => 1 + factor as u16,
+            Compression::Implode => 6,
             Compression::Deflate => 8,
             Compression::Deflate64 => 9,
             Compression::Bzip2 => 12,
             Compression::Lzma => 14,
             Compression::Zstd => 93,
             Compression::Xz => 95,
+            Compression::Ppmd => 98,
+            Compression::Other(method) => method,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "ppmd")]
+    use super::ppmd;
+    #[cfg(feature = "legacy")]
+    use super::{implode, reduce, shrink};
+
+    #[cfg(any(feature = "legacy", feature = "ppmd"))]
+    const TEXT: &[u8] = b"abracadabra, abracadabra! \x90\x90 cadabra abracadabra, abracadabra!";
+
+    #[test]
+    #[cfg(feature = "legacy")]
+    fn decode_shrink() {
+        let data = [
+            0x61, 0xc4, 0x04, 0x1c, 0x23, 0xb0, 0x60, 0x98, 0x83, 0x08, 0xc3, 0x00,
+        ];
+        assert_eq!(shrink::decompress(&data, 17).unwrap(), b"ababcbababaaaaaaa");
+
+        // Widens the code size early and partially clears the table halfway through.
+        let data = [
+            0x61, 0xc4, 0xc8, 0x01, 0x18, 0x20, 0x8c, 0x31, 0xc2, 0x20, 0x23, 0xa0, 0x81, 0x58,
+            0x00, 0x01, 0x21, 0x82, 0x0c, 0x62, 0x28, 0x04, 0x80, 0x04, 0x00, 0x01, 0x12, 0x48,
+            0x40, 0x18, 0xc3, 0x20, 0x86, 0x40, 0x60, 0x68, 0x21, 0x32, 0x18, 0x62, 0xe1, 0x21,
+            0x31, 0x06, 0x8a, 0x88, 0xa1, 0x10, 0x00,
+        ];
+        assert_eq!(shrink::decompress(&data, TEXT.len()).unwrap(), TEXT);
+        assert!(shrink::decompress(&[0xFF, 0x01], 2).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "legacy")]
+    fn decode_shrink_with_full_table() {
+        // Every literal after the first adds an entry, filling the table, and
+        // code 257 still refers to the first one added.
+        let literals: Vec<u8> = (0..8000u32).map(|idx| b'a' + (idx % 26) as u8).collect();
+        let (mut data, mut buffer, mut bits) = (Vec::new(), 0u32, 0);
+        for code in literals.iter().map(|&byte| byte as u32).chain([257]) {
+            buffer |= code << bits;
+            bits += 9;
+            while bits >= 8 {
+                data.push(buffer as u8);
+                buffer >>= 8;
+                bits -= 8;
+            }
         }
+        data.push(buffer as u8);
+
+        let expected = [literals.as_slice(), b"ab"].concat();
+        assert_eq!(shrink::decompress(&data, expected.len()).unwrap(), expected);
+    }
+
+    #[test]
+    #[cfg(feature = "legacy")]
+    fn decode_reduce() {
+        let data = [
+            vec![0; 83],
+            vec![0x10, 0x00, 0x0b, 0x09, 0x08],
+            vec![0; 21],
+            vec![0x40, 0x10, 0x06],
+            vec![0; 9],
+            vec![0x04, 0x90, 0x41, 0x58, 0x20, 0x37, 0x90, 0x8d, 0x89, 0x01],
+            vec![0; 38],
+            vec![
+                0x04, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x80, 0x04, 0x90, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x24, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x04, 0x21, 0x02, 0x24, 0x09, 0x10, 0x5c, 0x04, 0x0c, 0x40, 0xc0, 0x02, 0x04, 0x0b,
+                0x00, 0x10, 0x18, 0x0c, 0x90, 0x61, 0x20, 0x22, 0x08, 0x30, 0x00, 0xc0, 0x00, 0x00,
+                0x48, 0x02, 0x20, 0x48,
+            ],
+        ]
+        .concat();
+        assert_eq!(reduce::decompress(&data, TEXT.len(), 2).unwrap(), TEXT);
+        assert!(reduce::decompress(&data[..100], TEXT.len(), 2).is_err());
+        assert!(reduce::decompress(&data, TEXT.len(), 5).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "legacy")]
+    fn decode_implode() {
+        let data = [
+            0x0e, 0x05, 0x06, 0x03, 0x06, 0x05, 0x03, 0x25, 0x13, 0x06, 0x03, 0x66, 0xf5, 0xf6,
+            0x75, 0x36, 0x06, 0x01, 0x05, 0x96, 0xf5, 0xf5, 0xf6, 0x36, 0xc3, 0x8a, 0x95, 0x1b,
+            0x76, 0x6c, 0x58, 0x32, 0x86, 0x3f, 0x4b, 0x10, 0x8c, 0x77, 0x48, 0x90, 0x90, 0x21,
+            0x83, 0x58, 0x7c, 0x2e, 0x0f, 0xd2, 0x0d,
+        ];
+        assert_eq!(implode::decompress(&data, TEXT.len(), 0x1).unwrap(), TEXT);
+
+        let data = [
+            0x1b, 0x07, 0x98, 0xf7, 0x47, 0x04, 0x05, 0x97, 0x05, 0xf7, 0xf7, 0xf7, 0x37, 0x04,
+            0x25, 0xc7, 0x05, 0xf7, 0xc7, 0x04, 0x27, 0xf8, 0xf8, 0xf8, 0xf8, 0xf7, 0xf7, 0x77,
+            0x38, 0x0c, 0x05, 0x03, 0x16, 0x03, 0x25, 0x13, 0x05, 0x03, 0x76, 0xf5, 0xf6, 0x75,
+            0x36, 0x06, 0x01, 0x05, 0x96, 0xf5, 0xf5, 0xf6, 0x36, 0xdf, 0xfd, 0xf2, 0xdd, 0xbe,
+            0x2b, 0xe3, 0x7f, 0xfc, 0x98, 0xf7, 0xf9, 0xbf, 0xef, 0x6f, 0xf9, 0x5c, 0x0f, 0xf2,
+            0x06,
+        ];
+        assert_eq!(implode::decompress(&data, TEXT.len(), 0x2).unwrap(), TEXT);
+    }
+
+    #[test]
+    #[cfg(feature = "ppmd")]
+    fn round_trip_ppmd() {
+        let data = TEXT.repeat(16);
+        let compressed = ppmd::compress(&data).unwrap();
+        assert_eq!(u16::from_le_bytes([compressed[0], compressed[1]]), 0x00F5);
+        assert!(compressed.len() < data.len());
+        assert_eq!(ppmd::decompress(&compressed, data.len()).unwrap(), data);
+
+        assert!(ppmd::decompress(&[0xF5], 1).is_err());
+        assert!(ppmd::decompress(&[0xF5, 0x20, 0x00], 1).is_err());
     }
 }
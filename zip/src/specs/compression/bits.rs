@@ -0,0 +1,48 @@
+use crate::error::{ZipError, ZipResult};
+
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    available: u32,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            buffer: 0,
+            available: 0,
+        }
+    }
+
+    pub(crate) fn read(&mut self, count: u32) -> Option<u32> {
+        while self.available < count {
+            let byte = *self.data.get(self.position)?;
+            self.buffer |= (byte as u32) << self.available;
+            self.available += 8;
+            self.position += 1;
+        }
+
+        let value = self.buffer & ((1u64 << count) - 1) as u32;
+        self.buffer = self.buffer.checked_shr(count).unwrap_or(0);
+        self.available -= count;
+        Some(value)
+    }
+
+    pub(crate) fn expect(&mut self, count: u32) -> ZipResult<u32> {
+        self.read(count)
+            .ok_or_else(|| ZipError::InvalidArchive("Unexpected end of compressed data".into()))
+    }
+}
+
+pub(crate) fn copy_back(output: &mut Vec<u8>, distance: usize, length: usize, size: usize) {
+    for _ in 0..length.min(size.saturating_sub(output.len())) {
+        let byte = match output.len().checked_sub(distance) {
+            Some(position) => output[position],
+            None => 0,
+        };
+        output.push(byte);
+    }
+}
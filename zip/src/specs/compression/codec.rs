@@ -0,0 +1,121 @@
+use {
+    super::Compression,
+    crate::error::{ZipError, ZipResult},
+    std::{
+        collections::HashMap,
+        sync::{Arc, LazyLock, PoisonError, RwLock},
+    },
+};
+
+type Registry = HashMap<u16, Arc<dyn Codec>>;
+
+static CODECS: LazyLock<RwLock<Registry>> = LazyLock::new(Default::default);
+
+pub trait Codec: Send + Sync {
+    fn decode(&self, data: &[u8], size: usize) -> ZipResult<Vec<u8>>;
+
+    fn encode(&self, _data: &[u8]) -> ZipResult<Vec<u8>> {
+        Err(ZipError::CompressionNotSupported)
+    }
+}
+
+/// Registers `codec` for entries stored with compression `method`, replacing
+/// any codec previously registered for it. Built-in methods cannot be overridden.
+pub fn register_codec<C>(method: u16, codec: C) -> ZipResult<()>
+where
+    C: Codec + 'static,
+{
+    if !matches!(Compression::from(method), Compression::Other(_)) {
+        Err(ZipError::FeatureNotSupported(
+            format!("Compression method {method} is handled by a built-in codec").into(),
+        ))?
+    }
+
+    CODECS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(method, Arc::new(codec));
+    Ok(())
+}
+
+pub fn unregister_codec(method: u16) -> bool {
+    CODECS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(&method)
+        .is_some()
+}
+
+pub(crate) fn registered(method: u16) -> ZipResult<Arc<dyn Codec>> {
+    CODECS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&method)
+        .cloned()
+        .ok_or(ZipError::CompressionNotSupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{register_codec, unregister_codec, Codec},
+        crate::{
+            compression::Compression,
+            error::{ZipError, ZipResult},
+            write::{FileOptions, ZipWriter},
+            ZipArchive,
+        },
+        smol::io::Cursor,
+    };
+
+    #[test]
+    fn use_registered_codec() {
+        struct Invert;
+
+        impl Codec for Invert {
+            fn decode(&self, data: &[u8], _size: usize) -> ZipResult<Vec<u8>> {
+                Ok(data.iter().map(|byte| !byte).collect())
+            }
+
+            fn encode(&self, data: &[u8]) -> ZipResult<Vec<u8>> {
+                Ok(data.iter().map(|byte| !byte).collect())
+            }
+        }
+
+        smol::block_on(async {
+            const METHOD: u16 = 0x4242;
+            assert!(register_codec(8, Invert).is_err());
+            register_codec(METHOD, Invert).unwrap();
+
+            let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+            let options = FileOptions::new().compression(Compression::Other(METHOD));
+            writer.write_file("a.txt", b"alpha", options).await.unwrap();
+            let archive = writer.finish().await.unwrap().into_inner();
+
+            let mut zip = ZipArchive::new(Cursor::new(archive)).await.unwrap();
+            let file = zip.file_by_name("a.txt").await.unwrap();
+            assert_eq!(file.compression, Compression::Other(METHOD));
+            assert_eq!(&*file, &[!b'a', !b'l', !b'p', !b'h', !b'a']);
+            assert_eq!(file.extract().await.unwrap(), b"alpha");
+
+            assert!(unregister_codec(METHOD));
+            let file = zip.file_by_index(0).await.unwrap();
+            assert!(matches!(
+                file.extract().await,
+                Err(ZipError::CompressionNotSupported)
+            ));
+
+            let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+            writer.copy_file(&mut zip, 0).await.unwrap();
+            let copy = writer.finish().await.unwrap().into_inner();
+            let mut copy = ZipArchive::new(Cursor::new(copy)).await.unwrap();
+            let entry = copy.entry_by_index(0).unwrap();
+            assert_eq!(entry.compression(), Compression::Other(METHOD));
+            assert_eq!(entry.crc32(), crc32fast::hash(b"alpha"));
+
+            register_codec(METHOD, Invert).unwrap();
+            assert!(copy.verify().await.is_ok());
+            assert!(unregister_codec(METHOD));
+        })
+    }
+}
@@ -0,0 +1,110 @@
+use {
+    super::bits::{copy_back, BitReader},
+    crate::error::{ZipError, ZipResult},
+};
+
+const MAX_CODE_LENGTH: usize = 16;
+
+struct ShannonFano {
+    counts: [u16; MAX_CODE_LENGTH + 1],
+    symbols: Vec<u16>,
+}
+
+impl ShannonFano {
+    fn read(bits: &mut BitReader, symbols: usize) -> ZipResult<Self> {
+        let mut lengths = Vec::with_capacity(symbols);
+        for _ in 0..bits.expect(8)? + 1 {
+            let byte = bits.expect(8)?;
+            let length = (byte & 0xF) as u8 + 1;
+            let run = (byte >> 4) as usize + 1;
+            if lengths.len() + run > symbols {
+                Err(invalid("Implode tree describes too many symbols"))?
+            }
+            lengths.extend(std::iter::repeat_n(length, run));
+        }
+        if lengths.len() != symbols {
+            Err(invalid("Implode tree describes too few symbols"))?
+        }
+
+        let mut counts = [0u16; MAX_CODE_LENGTH + 1];
+        for &length in &lengths {
+            counts[length as usize] += 1;
+        }
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = left * 2 - count as i32;
+            if left < 0 {
+                Err(invalid("Implode tree is over-subscribed"))?
+            }
+        }
+
+        let mut symbols = Vec::with_capacity(lengths.len());
+        for length in 1..=MAX_CODE_LENGTH as u8 {
+            symbols.extend(
+                lengths
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &len)| len == length)
+                    .map(|(symbol, _)| symbol as u16),
+            );
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> ZipResult<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= (bits.expect(1)? ^ 1) as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("Invalid Implode code"))
+    }
+}
+
+pub(crate) fn decompress(data: &[u8], size: usize, options: u8) -> ZipResult<Vec<u8>> {
+    let large_window = options & 0x1 != 0;
+    let literal_tree = options & 0x2 != 0;
+
+    let mut bits = BitReader::new(data);
+    let literals = match literal_tree {
+        true => Some(ShannonFano::read(&mut bits, 256)?),
+        false => None,
+    };
+    let lengths = ShannonFano::read(&mut bits, 64)?;
+    let distances = ShannonFano::read(&mut bits, 64)?;
+    let distance_bits = if large_window { 7 } else { 6 };
+    let min_length = if literal_tree { 3 } else { 2 };
+
+    let mut output = Vec::with_capacity(size);
+    while output.len() < size {
+        if bits.expect(1)? == 1 {
+            let byte = match &literals {
+                Some(tree) => tree.decode(&mut bits)? as u8,
+                None => bits.expect(8)? as u8,
+            };
+            output.push(byte);
+            continue;
+        }
+
+        let low = bits.expect(distance_bits)? as usize;
+        let distance = (distances.decode(&mut bits)? as usize) << distance_bits | low;
+        let symbol = lengths.decode(&mut bits)? as usize;
+        let mut length = symbol + min_length;
+        if symbol == 63 {
+            length += bits.expect(8)? as usize;
+        }
+        copy_back(&mut output, distance + 1, length, size);
+    }
+
+    Ok(output)
+}
+
+fn invalid(message: &str) -> ZipError {
+    ZipError::InvalidArchive(message.into())
+}
@@ -0,0 +1,55 @@
+use {
+    crate::error::{ZipError, ZipResult},
+    ppmd_rust::{Ppmd8Decoder, Ppmd8Encoder, RestoreMethod},
+    std::io::{Read, Write},
+};
+
+const ORDER: u32 = 6;
+const MEMORY_SIZE_MB: u32 = 16;
+
+pub(crate) fn decompress(data: &[u8], size: usize) -> ZipResult<Vec<u8>> {
+    let (parameters, data) = data
+        .split_first_chunk::<2>()
+        .ok_or_else(|| invalid("PPMd stream is missing its parameters"))?;
+    let parameters = u16::from_le_bytes(*parameters);
+
+    let order = (parameters & 0xF) as u32 + 1;
+    let memory_size = (((parameters >> 4) & 0xFF) as u32 + 1) << 20;
+    let restore_method = match parameters >> 12 {
+        0 => RestoreMethod::Restart,
+        1 => RestoreMethod::CutOff,
+        _ => Err(invalid("Unsupported PPMd restore method"))?,
+    };
+
+    let decoder = Ppmd8Decoder::new(data, order, memory_size, restore_method).map_err(from_ppmd)?;
+    let mut output = Vec::with_capacity(size);
+    decoder.take(size as u64).read_to_end(&mut output)?;
+    Ok(output)
+}
+
+pub(crate) fn compress(data: &[u8]) -> ZipResult<Vec<u8>> {
+    let parameters = (ORDER - 1) as u16
+        | ((MEMORY_SIZE_MB - 1) as u16) << 4
+        | (RestoreMethod::Restart as u16) << 12;
+
+    let mut encoder = Ppmd8Encoder::new(
+        parameters.to_le_bytes().to_vec(),
+        ORDER,
+        MEMORY_SIZE_MB << 20,
+        RestoreMethod::Restart,
+    )
+    .map_err(from_ppmd)?;
+    encoder.write_all(data)?;
+    Ok(encoder.finish(true)?)
+}
+
+fn from_ppmd(error: ppmd_rust::Error) -> ZipError {
+    match error {
+        ppmd_rust::Error::IoError(error) => ZipError::IO(error),
+        error => invalid(&error.to_string()),
+    }
+}
+
+fn invalid(message: &str) -> ZipError {
+    ZipError::InvalidArchive(message.into())
+}
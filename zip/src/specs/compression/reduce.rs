@@ -0,0 +1,98 @@
+use {
+    super::bits::{copy_back, BitReader},
+    crate::error::{ZipError, ZipResult},
+};
+
+const DLE: u8 = 144;
+
+enum State {
+    Literal,
+    Escape,
+    Length { value: u8, length: usize },
+    Distance { value: u8, length: usize },
+}
+
+pub(crate) fn decompress(data: &[u8], size: usize, factor: u8) -> ZipResult<Vec<u8>> {
+    if !(1..=4).contains(&factor) {
+        Err(ZipError::CompressionNotSupported)?
+    }
+
+    let mut bits = BitReader::new(data);
+    let followers = read_follower_sets(&mut bits)?;
+    let length_mask = 0xFF >> factor;
+
+    let mut output = Vec::with_capacity(size);
+    let mut state = State::Literal;
+    let mut last = 0u8;
+    while output.len() < size {
+        let set = &followers[last as usize];
+        let byte = if set.is_empty() || bits.expect(1)? == 1 {
+            bits.expect(8)? as u8
+        } else {
+            let index = bits.expect(index_width(set.len()))? as usize;
+            *set.get(index)
+                .ok_or_else(|| invalid("Reduce follower index out of range"))?
+        };
+        last = byte;
+
+        state = match state {
+            State::Literal if byte == DLE => State::Escape,
+            State::Literal => {
+                output.push(byte);
+                State::Literal
+            }
+            State::Escape if byte == 0 => {
+                output.push(DLE);
+                State::Literal
+            }
+            State::Escape => {
+                let length = (byte & length_mask) as usize;
+                if length == length_mask as usize {
+                    State::Length {
+                        value: byte,
+                        length,
+                    }
+                } else {
+                    State::Distance {
+                        value: byte,
+                        length,
+                    }
+                }
+            }
+            State::Length { value, length } => State::Distance {
+                value,
+                length: length + byte as usize,
+            },
+            State::Distance { value, length } => {
+                let distance = ((value >> (8 - factor)) as usize) << 8 | byte as usize;
+                copy_back(&mut output, distance + 1, length + 3, size);
+                State::Literal
+            }
+        };
+    }
+
+    output.truncate(size);
+    Ok(output)
+}
+
+fn read_follower_sets(bits: &mut BitReader) -> ZipResult<Vec<Vec<u8>>> {
+    let mut followers = vec![Vec::new(); 256];
+    for set in followers.iter_mut().rev() {
+        let len = bits.expect(6)?;
+        if len > 32 {
+            Err(invalid("Reduce follower set is too large"))?
+        }
+        for _ in 0..len {
+            set.push(bits.expect(8)? as u8);
+        }
+    }
+    Ok(followers)
+}
+
+fn index_width(len: usize) -> u32 {
+    usize::BITS - (len.max(2) - 1).leading_zeros()
+}
+
+fn invalid(message: &str) -> ZipError {
+    ZipError::InvalidArchive(message.into())
+}
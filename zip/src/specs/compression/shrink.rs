@@ -0,0 +1,103 @@
+use {
+    super::bits::BitReader,
+    crate::error::{ZipError, ZipResult},
+};
+
+const CONTROL_CODE: u16 = 256;
+const FREE_CODE: u16 = u16::MAX;
+const MIN_CODE_SIZE: u32 = 9;
+const MAX_CODE_SIZE: u32 = 13;
+const TABLE_SIZE: usize = 1 << MAX_CODE_SIZE;
+
+pub(crate) fn decompress(data: &[u8], size: usize) -> ZipResult<Vec<u8>> {
+    let mut bits = BitReader::new(data);
+    let mut output = Vec::with_capacity(size);
+    let mut parent = vec![FREE_CODE; TABLE_SIZE];
+    let mut value = vec![0u8; TABLE_SIZE];
+    for code in 0..CONTROL_CODE {
+        parent[code as usize] = CONTROL_CODE;
+        value[code as usize] = code as u8;
+    }
+
+    let mut code_size = MIN_CODE_SIZE;
+    let mut previous = match bits.read(code_size) {
+        Some(code) if code < CONTROL_CODE as u32 => code as u16,
+        Some(_) => Err(invalid("Shrink stream starts with a non-literal code"))?,
+        None => return Ok(output),
+    };
+    let mut first = previous as u8;
+    output.push(first);
+
+    let mut last_free = CONTROL_CODE;
+    let mut stack = Vec::new();
+    while output.len() < size {
+        let Some(code) = bits.read(code_size) else {
+            break;
+        };
+        let code = code as u16;
+
+        if code == CONTROL_CODE {
+            match bits.read(code_size) {
+                Some(1) if code_size < MAX_CODE_SIZE => code_size += 1,
+                Some(2) => {
+                    partial_clear(&mut parent);
+                    last_free = CONTROL_CODE;
+                }
+                _ => Err(invalid("Invalid Shrink control code"))?,
+            }
+            continue;
+        }
+
+        stack.clear();
+        let mut current = code;
+        if parent[current as usize] == FREE_CODE {
+            stack.push(first);
+            current = previous;
+        }
+        while current != CONTROL_CODE {
+            if parent[current as usize] == FREE_CODE || stack.len() >= TABLE_SIZE {
+                Err(invalid("Shrink stream references an unused code"))?
+            }
+            stack.push(value[current as usize]);
+            current = parent[current as usize];
+        }
+        first = stack[stack.len() - 1];
+        output.extend(stack.iter().rev());
+
+        // A full table is left as is until the stream clears it.
+        match (last_free as usize + 1..TABLE_SIZE).find(|&code| parent[code] == FREE_CODE) {
+            Some(free) => {
+                last_free = free as u16;
+                parent[free] = previous;
+                value[free] = first;
+            }
+            None => last_free = TABLE_SIZE as u16 - 1,
+        }
+        previous = code;
+    }
+
+    output.truncate(size);
+    Ok(output)
+}
+
+fn partial_clear(parent: &mut [u16]) {
+    let mut has_child = vec![false; TABLE_SIZE];
+    for &prefix in &parent[CONTROL_CODE as usize + 1..] {
+        if prefix != FREE_CODE && prefix > CONTROL_CODE {
+            has_child[prefix as usize] = true;
+        }
+    }
+    for (code, prefix) in parent
+        .iter_mut()
+        .enumerate()
+        .skip(CONTROL_CODE as usize + 1)
+    {
+        if !has_child[code] {
+            *prefix = FREE_CODE;
+        }
+    }
+}
+
+fn invalid(message: &str) -> ZipError {
+    ZipError::InvalidArchive(message.into())
+}
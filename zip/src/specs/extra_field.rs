@@ -85,8 +85,8 @@ impl ExtraFieldAsBytes for UnknownExtraField {
         let content = &*self.content;
         [header_id, data_size, content]
             .iter()
-            .flat_map(|section| section.into_iter())
-            .map(|b| *b)
+            .flat_map(|section| section.iter())
+            .copied()
             .collect()
     }
 
@@ -117,7 +117,7 @@ impl ExtraFieldAsBytes for ZipUnicodeCommentInfoExtraField {
                 [data_size, &[*version], data]
                     .iter()
                     .flat_map(|f| f.iter())
-                    .map(|b| *b)
+                    .copied()
                     .collect()
             }
         }
@@ -151,7 +151,7 @@ impl ExtraFieldAsBytes for ZipUnicodePathInfoExtraField {
                 [data_size, &[*version], data]
                     .iter()
                     .flat_map(|f| f.iter())
-                    .map(|b| *b)
+                    .copied()
                     .collect()
             }
         }
@@ -195,7 +195,7 @@ impl ExtraFieldAsBytes for Zip64ExtendedInfoExtraField {
         ]
         .iter()
         .flat_map(|f| f.iter())
-        .map(|b| *b)
+        .copied()
         .collect()
     }
 
@@ -210,6 +210,12 @@ impl HeaderId {
     pub const ZIP_UNICODE_PATH_INFO_EXTRA_FIELD: Self = Self(0x7075);
 }
 
+impl Default for Zip64ExtendedInfoExtraField {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Zip64ExtendedInfoExtraField {
     pub fn new() -> Self {
         Self {
@@ -244,26 +250,25 @@ impl Zip64ExtendedInfoExtraField {
     {
         let data = data.as_ref();
         let mut current_index = 0;
-        let uncompressed_size =
-            if uncompressed_size == u32::MAX && data.len() >= current_index + 8 {
-                let val = Some(u64::from_le_bytes(
-                    data[current_index..current_index + 8].try_into().unwrap(),
-                ));
-                current_index += 8;
-                val
-            } else {
-                None
-            };
-        let compressed_size =
-            if compressed_size == u32::MAX && data.len() >= current_index + 8 {
-                let val = Some(u64::from_le_bytes(
-                    data[current_index..current_index + 8].try_into().unwrap(),
-                ));
-                current_index += 8;
-                val
-            } else {
-                None
-            };
+        let uncompressed_size = if uncompressed_size == u32::MAX && data.len() >= current_index + 8
+        {
+            let val = Some(u64::from_le_bytes(
+                data[current_index..current_index + 8].try_into().unwrap(),
+            ));
+            current_index += 8;
+            val
+        } else {
+            None
+        };
+        let compressed_size = if compressed_size == u32::MAX && data.len() >= current_index + 8 {
+            let val = Some(u64::from_le_bytes(
+                data[current_index..current_index + 8].try_into().unwrap(),
+            ));
+            current_index += 8;
+            val
+        } else {
+            None
+        };
         let relative_header_offset = if data.len() >= current_index + 8 {
             let val = Some(u64::from_le_bytes(
                 data[current_index..current_index + 8].try_into().unwrap(),
@@ -291,6 +296,21 @@ impl Zip64ExtendedInfoExtraField {
     }
 }
 
+/// The whole zip64 extended information block among the `extra` fields of a
+/// header, including its id and size.
+pub(crate) fn zip64_block(extra: &[u8]) -> Option<&[u8]> {
+    let mut rest = extra;
+    while rest.len() >= 4 {
+        let header_id = HeaderId(u16::from_le_bytes([rest[0], rest[1]]));
+        let end = (4 + u16::from_le_bytes([rest[2], rest[3]]) as usize).min(rest.len());
+        if header_id == HeaderId::ZIP64_EXTENDED_INFO_EXTRA_FIELD {
+            return Some(&rest[..end]);
+        }
+        rest = &rest[end..];
+    }
+    None
+}
+
 impl ZipUnicodeCommentInfoExtraField {
     pub fn from_bytes<A>(_header_id: HeaderId, data_size: u16, data: A) -> ZipResult<Self>
     where
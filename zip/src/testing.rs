@@ -0,0 +1,253 @@
+use std::{
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+pub(crate) fn build_volumes(files: &[(&str, &[u8])], split: Option<usize>) -> Vec<Vec<u8>> {
+    let mut volumes = vec![Vec::new()];
+    let mut central_directory = Vec::new();
+
+    for (idx, (name, data)) in files.iter().enumerate() {
+        if Some(idx) == split {
+            volumes.push(Vec::new());
+        }
+        let disk = volumes.len() as u16 - 1;
+        let volume = volumes.last_mut().unwrap();
+        let offset = volume.len() as u32;
+        let mode: u32 = if name.ends_with('/') {
+            0o040755
+        } else {
+            0o100644
+        };
+
+        volume.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        volume.extend_from_slice(&20u16.to_le_bytes());
+        volume.extend_from_slice(&0u16.to_le_bytes());
+        volume.extend_from_slice(&0u16.to_le_bytes());
+        volume.extend_from_slice(&[0, 0, 0x21, 0]);
+        volume.extend_from_slice(&crc32fast::hash(data).to_le_bytes());
+        volume.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        volume.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        volume.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        volume.extend_from_slice(&0u16.to_le_bytes());
+        volume.extend_from_slice(name.as_bytes());
+        volume.extend_from_slice(data);
+
+        central_directory.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central_directory.extend_from_slice(&[20, 3]);
+        central_directory.extend_from_slice(&20u16.to_le_bytes());
+        central_directory.extend_from_slice(&0u16.to_le_bytes());
+        central_directory.extend_from_slice(&0u16.to_le_bytes());
+        central_directory.extend_from_slice(&[0, 0, 0x21, 0]);
+        central_directory.extend_from_slice(&crc32fast::hash(data).to_le_bytes());
+        central_directory.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        central_directory.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        central_directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        central_directory.extend_from_slice(&0u16.to_le_bytes());
+        central_directory.extend_from_slice(&0u16.to_le_bytes());
+        central_directory.extend_from_slice(&disk.to_le_bytes());
+        central_directory.extend_from_slice(&0u16.to_le_bytes());
+        central_directory.extend_from_slice(&(mode << 16).to_le_bytes());
+        central_directory.extend_from_slice(&offset.to_le_bytes());
+        central_directory.extend_from_slice(name.as_bytes());
+    }
+
+    let disk = volumes.len() as u16 - 1;
+    let volume = volumes.last_mut().unwrap();
+    let offset = volume.len() as u32;
+    volume.extend_from_slice(&central_directory);
+    volume.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    volume.extend_from_slice(&disk.to_le_bytes());
+    volume.extend_from_slice(&disk.to_le_bytes());
+    volume.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    volume.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    volume.extend_from_slice(&(central_directory.len() as u32).to_le_bytes());
+    volume.extend_from_slice(&offset.to_le_bytes());
+    volume.extend_from_slice(&0u16.to_le_bytes());
+    volumes
+}
+
+pub(crate) fn build_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    build_volumes(files, None).concat()
+}
+
+fn read_u16(archive: &[u8], at: usize) -> usize {
+    u16::from_le_bytes([archive[at], archive[at + 1]]) as usize
+}
+
+/// Offset of the central directory record of entry `index` in an archive
+/// without a comment.
+pub(crate) fn record_offset(archive: &[u8], index: usize) -> usize {
+    let end = archive.len() - 22;
+    let mut offset = u32::from_le_bytes(archive[end + 16..end + 20].try_into().unwrap()) as usize;
+    for _ in 0..index {
+        offset += 46
+            + read_u16(archive, offset + 28)
+            + read_u16(archive, offset + 30)
+            + read_u16(archive, offset + 32);
+    }
+    offset
+}
+
+/// Offset of the local header of entry `index`.
+pub(crate) fn header_offset(archive: &[u8], index: usize) -> usize {
+    let record = record_offset(archive, index);
+    u32::from_le_bytes(archive[record + 42..record + 46].try_into().unwrap()) as usize
+}
+
+/// Offset of the data of entry `index`.
+pub(crate) fn data_offset(archive: &[u8], index: usize) -> usize {
+    let header = header_offset(archive, index);
+    header + 30 + read_u16(archive, header + 26) + read_u16(archive, header + 28)
+}
+
+/// Sets the general purpose flags of entry `index` in both of its headers.
+pub(crate) fn set_flags(archive: &mut [u8], index: usize, flags: u16) {
+    let (header, record) = (header_offset(archive, index), record_offset(archive, index));
+    archive[header + 6..header + 8].copy_from_slice(&flags.to_le_bytes());
+    archive[record + 8..record + 10].copy_from_slice(&flags.to_le_bytes());
+}
+
+/// A single stored entry followed by a zip64 data descriptor, announced by
+/// an empty zip64 block in its local header.
+pub(crate) fn build_zip64_descriptor_archive(name: &str, data: &[u8]) -> Vec<u8> {
+    let crc32 = crc32fast::hash(data);
+    let mut archive = Vec::new();
+    archive.extend_from_slice(&0x04034b50u32.to_le_bytes());
+    archive.extend_from_slice(&45u16.to_le_bytes());
+    archive.extend_from_slice(&8u16.to_le_bytes());
+    archive.extend_from_slice(&[0, 0, 0, 0, 0x21, 0]);
+    archive.extend_from_slice(&[0; 12]);
+    archive.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&20u16.to_le_bytes());
+    archive.extend_from_slice(name.as_bytes());
+    archive.extend_from_slice(&0x0001u16.to_le_bytes());
+    archive.extend_from_slice(&16u16.to_le_bytes());
+    archive.extend_from_slice(&[0; 16]);
+    archive.extend_from_slice(data);
+    archive.extend_from_slice(&0x08074b50u32.to_le_bytes());
+    archive.extend_from_slice(&crc32.to_le_bytes());
+    archive.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    archive.extend_from_slice(&(data.len() as u64).to_le_bytes());
+
+    let offset = archive.len() as u32;
+    archive.extend_from_slice(&0x02014b50u32.to_le_bytes());
+    archive.extend_from_slice(&[45, 3]);
+    archive.extend_from_slice(&45u16.to_le_bytes());
+    archive.extend_from_slice(&8u16.to_le_bytes());
+    archive.extend_from_slice(&[0, 0, 0, 0, 0x21, 0]);
+    archive.extend_from_slice(&crc32.to_le_bytes());
+    archive.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&[0; 8]);
+    archive.extend_from_slice(&(0o100644u32 << 16).to_le_bytes());
+    archive.extend_from_slice(&0u32.to_le_bytes());
+    archive.extend_from_slice(name.as_bytes());
+    let size = archive.len() as u32 - offset;
+
+    archive.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    archive.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+    archive.extend_from_slice(&size.to_le_bytes());
+    archive.extend_from_slice(&offset.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes());
+    archive
+}
+
+/// A stored entry whose sizes follow its data in a signed data descriptor.
+pub(crate) fn streamed_entry(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut entry = Vec::new();
+    entry.extend_from_slice(&0x04034b50u32.to_le_bytes());
+    entry.extend_from_slice(&20u16.to_le_bytes());
+    entry.extend_from_slice(&0x08u16.to_le_bytes());
+    entry.extend_from_slice(&0u16.to_le_bytes());
+    entry.extend_from_slice(&[0, 0, 0x21, 0]);
+    entry.extend_from_slice(&[0; 12]);
+    entry.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    entry.extend_from_slice(&0u16.to_le_bytes());
+    entry.extend_from_slice(name.as_bytes());
+    entry.extend_from_slice(data);
+    entry.extend_from_slice(&0x08074b50u32.to_le_bytes());
+    entry.extend_from_slice(&crc32fast::hash(data).to_le_bytes());
+    entry.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    entry.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    entry
+}
+
+/// A stored entry whose sizes are only in the zip64 block of its local header.
+pub(crate) fn zip64_entry(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut entry = Vec::new();
+    entry.extend_from_slice(&0x04034b50u32.to_le_bytes());
+    entry.extend_from_slice(&45u16.to_le_bytes());
+    entry.extend_from_slice(&0u16.to_le_bytes());
+    entry.extend_from_slice(&0u16.to_le_bytes());
+    entry.extend_from_slice(&[0, 0, 0x21, 0]);
+    entry.extend_from_slice(&crc32fast::hash(data).to_le_bytes());
+    entry.extend_from_slice(&[0xff; 8]);
+    entry.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    entry.extend_from_slice(&20u16.to_le_bytes());
+    entry.extend_from_slice(name.as_bytes());
+    entry.extend_from_slice(&0x0001u16.to_le_bytes());
+    entry.extend_from_slice(&16u16.to_le_bytes());
+    entry.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    entry.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    entry.extend_from_slice(data);
+    entry
+}
+
+/// A directory under the system temporary directory, removed on drop.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("libzip_rs-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// `build_archive` with a zip64 end record and locator, and a comment of
+/// `comment_len` bytes pushing them out of the end-of-archive search.
+pub(crate) fn build_zip64_archive(files: &[(&str, &[u8])], comment_len: u16) -> Vec<u8> {
+    let mut archive = build_archive(files);
+    let eocd = archive.split_off(archive.len() - 22);
+    let size = u32::from_le_bytes(eocd[12..16].try_into().unwrap()) as u64;
+    let offset = u32::from_le_bytes(eocd[16..20].try_into().unwrap()) as u64;
+    let record = archive.len() as u64;
+
+    archive.extend_from_slice(&0x06064b50u32.to_le_bytes());
+    archive.extend_from_slice(&44u64.to_le_bytes());
+    archive.extend_from_slice(&[45, 3, 45, 0]);
+    archive.extend_from_slice(&[0; 8]);
+    archive.extend_from_slice(&(files.len() as u64).to_le_bytes());
+    archive.extend_from_slice(&(files.len() as u64).to_le_bytes());
+    archive.extend_from_slice(&size.to_le_bytes());
+    archive.extend_from_slice(&offset.to_le_bytes());
+
+    archive.extend_from_slice(&0x07064b50u32.to_le_bytes());
+    archive.extend_from_slice(&0u32.to_le_bytes());
+    archive.extend_from_slice(&record.to_le_bytes());
+    archive.extend_from_slice(&1u32.to_le_bytes());
+
+    archive.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    archive.extend_from_slice(&[0; 4]);
+    archive.extend_from_slice(&[0xff; 12]);
+    archive.extend_from_slice(&comment_len.to_le_bytes());
+    archive.resize(archive.len() + comment_len as usize, b'#');
+    archive
+}
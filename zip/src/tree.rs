@@ -0,0 +1,317 @@
+use {
+    crate::{
+        datetime::ZipDateTime,
+        error::{ZipError, ZipResult},
+        specs::ZipEntry,
+        ZipArchive,
+    },
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        ffi::{OsStr, OsString},
+        io,
+        ops::Deref,
+        path::{Component, Path, PathBuf},
+    },
+};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NodeKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Metadata {
+    kind: NodeKind,
+    index: Option<usize>,
+    len: u64,
+    modified: Option<ZipDateTime>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DirEntry {
+    path: PathBuf,
+    metadata: Metadata,
+}
+
+#[derive(Debug)]
+struct Node {
+    metadata: Metadata,
+    children: BTreeSet<OsString>,
+}
+
+#[derive(Debug)]
+pub struct ZipTree {
+    nodes: BTreeMap<PathBuf, Node>,
+}
+
+impl Metadata {
+    fn directory() -> Self {
+        Self {
+            kind: NodeKind::Directory,
+            index: None,
+            len: 0,
+            modified: None,
+        }
+    }
+
+    fn from_entry(index: usize, entry: &ZipEntry) -> Self {
+        let kind = if entry.is_dir() {
+            NodeKind::Directory
+        } else if entry.is_symlink() {
+            NodeKind::Symlink
+        } else {
+            NodeKind::File
+        };
+
+        Self {
+            kind,
+            index: Some(index),
+            len: entry.uncompressed_size(),
+            modified: Some(entry.last_modified()),
+        }
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == NodeKind::Directory
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.kind == NodeKind::File
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.kind == NodeKind::Symlink
+    }
+
+    pub fn is_implicit(&self) -> bool {
+        self.index.is_none()
+    }
+
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn modified(&self) -> Option<ZipDateTime> {
+        self.modified
+    }
+}
+
+impl DirEntry {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn file_name(&self) -> &OsStr {
+        self.path.file_name().unwrap_or_default()
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
+impl ZipTree {
+    pub(crate) fn new<I>(entries: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Deref<Target = ZipEntry>,
+    {
+        let mut nodes = BTreeMap::new();
+        nodes.insert(PathBuf::new(), Node::new(Metadata::directory()));
+
+        for (index, entry) in entries.into_iter().enumerate() {
+            let path = normalize(entry.name().as_os_str());
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+
+            // A path used both as a file and as a directory is a directory,
+            // whichever entry comes first.
+            for ancestor in path.ancestors().skip(1) {
+                let node = nodes
+                    .entry(ancestor.to_path_buf())
+                    .or_insert_with(|| Node::new(Metadata::directory()));
+                if !node.metadata.is_dir() {
+                    node.metadata = Metadata::directory();
+                }
+            }
+            for (parent, child) in path.ancestors().skip(1).zip(path.ancestors()) {
+                if let (Some(node), Some(name)) = (nodes.get_mut(parent), child.file_name()) {
+                    node.children.insert(name.to_os_string());
+                }
+            }
+
+            let metadata = Metadata::from_entry(index, &entry);
+            match nodes.get_mut(&path) {
+                Some(node) if node.metadata.is_dir() && !metadata.is_dir() => (),
+                Some(node) => node.metadata = metadata,
+                None => {
+                    nodes.insert(path, Node::new(metadata));
+                }
+            }
+        }
+
+        Self { nodes }
+    }
+
+    pub fn exists<P>(&self, path: P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        self.nodes.contains_key(&normalize(path))
+    }
+
+    pub fn metadata<P>(&self, path: P) -> ZipResult<Metadata>
+    where
+        P: AsRef<Path>,
+    {
+        self.node(path.as_ref()).map(|node| node.metadata.clone())
+    }
+
+    pub fn read_dir<P>(&self, path: P) -> ZipResult<Vec<DirEntry>>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let node = self.node(path)?;
+        if !node.metadata.is_dir() {
+            Err(ZipError::IO(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                format!("{} is not a directory", path.display()),
+            )))?
+        }
+
+        let parent = normalize(path);
+        Ok(node
+            .children
+            .iter()
+            .filter_map(|name| {
+                let path = parent.join(name);
+                let metadata = self.nodes.get(&path)?.metadata.clone();
+                Some(DirEntry { path, metadata })
+            })
+            .collect())
+    }
+
+    fn node(&self, path: &Path) -> ZipResult<&Node> {
+        match self.nodes.get(&normalize(path)) {
+            Some(node) => Ok(node),
+            None => Err(ZipError::IO(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} does not exist in the archive", path.display()),
+            ))),
+        }
+    }
+}
+
+impl Node {
+    fn new(metadata: Metadata) -> Self {
+        Self {
+            metadata,
+            children: BTreeSet::new(),
+        }
+    }
+}
+
+impl<R> ZipArchive<R> {
+    pub fn tree(&self) -> ZipTree {
+        ZipTree::new(self.entries())
+    }
+}
+
+fn normalize<P>(path: P) -> PathBuf
+where
+    P: AsRef<Path>,
+{
+    path.as_ref()
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{testing::build_archive, ZipArchive},
+        smol::io::Cursor,
+    };
+
+    #[test]
+    fn browse_implicit_directories() {
+        smol::block_on(async {
+            let files: [(&str, &[u8]); 4] = [
+                ("assets/", b""),
+                ("assets/fonts/mono.ttf", b"font"),
+                ("assets/icons/app.svg", b"<svg/>"),
+                ("license.txt", b"MIT"),
+            ];
+            let zip = ZipArchive::new(Cursor::new(build_archive(&files)))
+                .await
+                .unwrap();
+            let tree = zip.tree();
+
+            let root: Vec<_> = tree
+                .read_dir("/")
+                .unwrap()
+                .into_iter()
+                .map(|entry| (entry.path().to_owned(), entry.metadata().is_dir()))
+                .collect();
+            assert_eq!(
+                root,
+                [("assets".into(), true), ("license.txt".into(), false)]
+            );
+
+            let fonts = tree.metadata("assets/fonts").unwrap();
+            assert!(fonts.is_dir() && fonts.is_implicit());
+            assert!(!tree.metadata("assets/").unwrap().is_implicit());
+
+            let icon = tree.metadata("assets/icons/app.svg").unwrap();
+            assert_eq!((icon.len(), icon.index()), (6, Some(2)));
+            assert_eq!(tree.read_dir("assets").unwrap().len(), 2);
+            assert!(tree.exists("assets/fonts/mono.ttf"));
+            assert!(!tree.exists("assets/fonts/sans.ttf"));
+            assert!(tree.read_dir("license.txt").is_err());
+            assert!(tree.metadata("missing").is_err());
+        })
+    }
+
+    #[test]
+    fn prefer_directories_in_tree() {
+        smol::block_on(async {
+            let mut files: [(&str, &[u8]); 4] = [
+                ("a", b"file"),
+                ("a/b.txt", b"child"),
+                ("c", b"file"),
+                ("c/", b""),
+            ];
+            for _ in 0..2 {
+                files.reverse();
+                let zip = ZipArchive::new(Cursor::new(build_archive(&files)))
+                    .await
+                    .unwrap();
+                let tree = zip.tree();
+
+                let a = tree.metadata("a").unwrap();
+                assert!(a.is_dir() && a.is_implicit());
+                assert_eq!(tree.read_dir("a").unwrap().len(), 1);
+                assert!(tree.metadata("a/b.txt").unwrap().is_file());
+                let c = tree.metadata("c").unwrap();
+                assert!(c.is_dir() && !c.is_implicit());
+            }
+        })
+    }
+}
@@ -0,0 +1,385 @@
+use {
+    crate::{
+        error::ZipError,
+        index::Storage,
+        path::ZipPath,
+        read::{volume::Volumes, ZipAsyncReadExt},
+        specs::ZipEntry,
+        ZipArchive,
+    },
+    futures_lite::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, SeekFrom},
+    std::io,
+};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum HeaderField {
+    FileName,
+    Compression,
+    Flags,
+    Crc32,
+    CompressedSize,
+    UncompressedSize,
+}
+
+#[derive(Debug)]
+pub enum Problem {
+    Unreadable(ZipError),
+    HeaderMismatch(HeaderField),
+    DescriptorMismatch(HeaderField),
+    OutOfBounds {
+        start: u64,
+        end: u64,
+    },
+    Overlap {
+        index: usize,
+    },
+    /// Another entry before this one has the same name.
+    DuplicateName {
+        index: usize,
+    },
+    Decode(ZipError),
+    SizeMismatch {
+        expected: u64,
+        actual: u64,
+    },
+    CrcMismatch {
+        expected: u32,
+        actual: u32,
+    },
+}
+
+#[derive(Debug)]
+pub struct Issue {
+    index: usize,
+    name: ZipPath,
+    problem: Problem,
+}
+
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    checked: usize,
+    skipped: usize,
+    issues: Vec<Issue>,
+}
+
+impl Issue {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn name(&self) -> &ZipPath {
+        &self.name
+    }
+
+    pub fn problem(&self) -> &Problem {
+        &self.problem
+    }
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Entries whose data was read and checked.
+    pub fn checked(&self) -> usize {
+        self.checked
+    }
+
+    /// Encrypted entries, whose data is not checked.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+
+    pub fn into_issues(self) -> Vec<Issue> {
+        self.issues
+    }
+}
+
+impl<R, I> ZipArchive<R, I>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    I: Storage,
+{
+    pub async fn verify(&mut self) -> VerifyReport {
+        let mut report = VerifyReport::default();
+        let mut spans = Vec::with_capacity(self.entries.len());
+
+        for (index, entry) in self.entries.values().enumerate() {
+            let mut problems = Vec::new();
+            let span = verify_entry(&mut self.reader, &self.volumes, &entry, &mut problems).await;
+
+            if let Some((start, end)) = span {
+                if end > self.central_directory_offset {
+                    problems.push(Problem::OutOfBounds { start, end });
+                }
+                spans.push((start, end, index));
+            }
+            match entry.is_encrypted() {
+                true => report.skipped += 1,
+                false => report.checked += 1,
+            }
+            report
+                .issues
+                .extend(problems.into_iter().map(|problem| Issue {
+                    index,
+                    name: entry.file_name.clone(),
+                    problem,
+                }));
+        }
+
+        for (name, indices) in self.duplicates() {
+            report
+                .issues
+                .extend(indices[1..].iter().map(|&index| Issue {
+                    index,
+                    name: name.clone(),
+                    problem: Problem::DuplicateName { index: indices[0] },
+                }));
+        }
+
+        spans.sort_unstable();
+        let mut furthest: Option<(u64, usize)> = None;
+        for (start, end, index) in spans {
+            match furthest {
+                Some((until, owner)) if start < until => {
+                    report.issues.push(Issue {
+                        index,
+                        name: self
+                            .entries
+                            .get_index(index)
+                            .map(|entry| entry.file_name.clone())
+                            .unwrap_or_default(),
+                        problem: Problem::Overlap { index: owner },
+                    });
+                    if end > until {
+                        furthest = Some((end, index));
+                    }
+                }
+                _ => furthest = Some((end, index)),
+            }
+        }
+
+        report.issues.sort_by_key(|issue| issue.index);
+        report
+    }
+}
+
+/// Whether a size from a local header disagrees with the central directory.
+/// Saturated sizes are kept in a zip64 block instead.
+fn size_differs(stored: u32, size: u64) -> bool {
+    stored != u32::MAX && stored as u64 != size
+}
+
+async fn verify_entry<R>(
+    reader: &mut R,
+    volumes: &Volumes,
+    entry: &ZipEntry,
+    problems: &mut Vec<Problem>,
+) -> Option<(u64, u64)>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let start = match volumes.resolve(entry.disk_start as u32, entry.file_header_offset) {
+        Ok(start) => start,
+        Err(err) => {
+            problems.push(Problem::Unreadable(err));
+            return None;
+        }
+    };
+    let header = match reader.seek(SeekFrom::Start(start)).await {
+        Ok(_) => reader.read_local_header().await,
+        Err(err) => Err(err.into()),
+    };
+    let header = match header {
+        Ok(header) => header,
+        Err(err) => {
+            problems.push(Problem::Unreadable(err));
+            return None;
+        }
+    };
+
+    let mismatches = [
+        (HeaderField::FileName, header.file_name != entry.file_name),
+        (
+            HeaderField::Compression,
+            header.compression != entry.compression,
+        ),
+        (HeaderField::Flags, header.flags != entry.flags),
+    ];
+    let sizes = [
+        (HeaderField::Crc32, header.crc32 != entry.crc32),
+        (
+            HeaderField::CompressedSize,
+            size_differs(header.compressed_size, entry.compressed_size),
+        ),
+        (
+            HeaderField::UncompressedSize,
+            size_differs(header.uncompressed_size, entry.uncompressed_size),
+        ),
+    ];
+    let deferred = header.flags.data_drescriptor;
+    problems.extend(
+        mismatches
+            .into_iter()
+            .chain(sizes.into_iter().filter(|_| !deferred))
+            .filter(|(_, mismatch)| *mismatch)
+            .map(|(field, _)| Problem::HeaderMismatch(field)),
+    );
+
+    let mut end = start + header.length + entry.compressed_size;
+    let mut data = Vec::with_capacity(entry.compressed_size as usize);
+    if let Err(err) = (&mut *reader)
+        .take(entry.compressed_size)
+        .read_to_end(&mut data)
+        .await
+    {
+        problems.push(Problem::Unreadable(err.into()));
+        return Some((start, end));
+    }
+    if data.len() < entry.compressed_size as usize {
+        problems.push(Problem::Unreadable(
+            io::Error::from(io::ErrorKind::UnexpectedEof).into(),
+        ));
+        return Some((start, end));
+    }
+
+    if entry.flags.data_drescriptor {
+        match reader.read_data_descriptor(header.zip64).await {
+            Ok((descriptor, length)) => {
+                end += length;
+                let mismatches = [
+                    (HeaderField::Crc32, descriptor.crc32 != entry.crc32),
+                    (
+                        HeaderField::CompressedSize,
+                        descriptor.compressed_size != entry.compressed_size,
+                    ),
+                    (
+                        HeaderField::UncompressedSize,
+                        descriptor.uncompressed_size != entry.uncompressed_size,
+                    ),
+                ];
+                problems.extend(
+                    mismatches
+                        .into_iter()
+                        .filter(|(_, mismatch)| *mismatch)
+                        .map(|(field, _)| Problem::DescriptorMismatch(field)),
+                );
+            }
+            Err(err) => problems.push(Problem::Unreadable(err)),
+        }
+    }
+
+    if entry.is_encrypted() {
+        return Some((start, end));
+    }
+
+    let mut file = header.into_zipfile(data);
+    file.compression = entry.compression;
+    file.sizes_from(entry);
+    match file.extract().await {
+        Ok(content) => {
+            if content.len() as u64 != entry.uncompressed_size {
+                problems.push(Problem::SizeMismatch {
+                    expected: entry.uncompressed_size,
+                    actual: content.len() as u64,
+                });
+            }
+            let crc32 = crc32fast::hash(&content);
+            if crc32 != entry.crc32 {
+                problems.push(Problem::CrcMismatch {
+                    expected: entry.crc32,
+                    actual: crc32,
+                });
+            }
+        }
+        Err(err) => problems.push(Problem::Decode(err)),
+    }
+    Some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{HeaderField, Problem},
+        crate::{
+            testing::{
+                build_archive, build_zip64_descriptor_archive, data_offset, header_offset,
+                record_offset, set_flags,
+            },
+            ZipArchive,
+        },
+        smol::io::Cursor,
+    };
+
+    #[test]
+    fn verify_archive_integrity() {
+        smol::block_on(async {
+            let files: [(&str, &[u8]); 3] = [("a.txt", b"alpha"), ("b.txt", b"bravo"), ("c/", b"")];
+            let archive = build_archive(&files);
+
+            let mut zip = ZipArchive::new(Cursor::new(archive.clone())).await.unwrap();
+            let report = zip.verify().await;
+            assert!(report.is_ok());
+            assert_eq!((report.checked(), report.skipped()), (3, 0));
+
+            let mut damaged = archive.clone();
+            damaged[data_offset(&archive, 0)] ^= 0xff;
+            damaged[header_offset(&archive, 1) + 30] = b'c';
+            let mut zip = ZipArchive::new(Cursor::new(damaged)).await.unwrap();
+            let report = zip.verify().await;
+            let problems: Vec<_> = report
+                .issues()
+                .iter()
+                .map(|issue| (issue.index(), issue.problem()))
+                .collect();
+            assert!(matches!(
+                problems[..],
+                [
+                    (0, Problem::CrcMismatch { .. }),
+                    (1, Problem::HeaderMismatch(HeaderField::FileName)),
+                ]
+            ));
+
+            let mut overlapping = archive.clone();
+            let record = record_offset(&archive, 1);
+            overlapping[record + 42..record + 46].copy_from_slice(&0u32.to_le_bytes());
+            let mut zip = ZipArchive::new(Cursor::new(overlapping)).await.unwrap();
+            let report = zip.verify().await;
+            assert!(report.issues().iter().any(|issue| issue.index() == 1
+                && matches!(issue.problem(), Problem::Overlap { index: 0 })));
+
+            let mut encrypted = archive;
+            set_flags(&mut encrypted, 1, 1);
+            let mut zip = ZipArchive::new(Cursor::new(encrypted)).await.unwrap();
+            let report = zip.verify().await;
+            assert!(report.is_ok());
+            assert_eq!((report.checked(), report.skipped()), (2, 1));
+        })
+    }
+
+    #[test]
+    fn verify_zip64_data_descriptor() {
+        smol::block_on(async {
+            let archive = build_zip64_descriptor_archive("big.bin", b"sixty-four");
+            let mut zip = ZipArchive::new(Cursor::new(archive.clone())).await.unwrap();
+            let report = zip.verify().await;
+            assert!(report.is_ok(), "{:?}", report.issues());
+            assert_eq!(report.checked(), 1);
+
+            let mut damaged = archive;
+            let size = data_offset(&damaged, 0) + b"sixty-four".len() + 8;
+            damaged[size] ^= 0x01;
+            let mut zip = ZipArchive::new(Cursor::new(damaged)).await.unwrap();
+            let report = zip.verify().await;
+            assert!(matches!(
+                report.issues()[0].problem(),
+                Problem::DescriptorMismatch(HeaderField::CompressedSize)
+            ));
+        })
+    }
+}
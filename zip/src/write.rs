@@ -0,0 +1,779 @@
+use {
+    crate::{
+        compression::Compression,
+        datetime::ZipDateTime,
+        error::{ZipError, ZipResult},
+        path::{Sanitize, ZipPath},
+        specs::{
+            attribute::{AttributeCompatibility, Attributes},
+            GeneralPurposeFlag, Zip32CentralDirectoryEndRecord, ZipEntry, ZipSpecs,
+            DATA_DESCRIPTOR_SIGNATURE,
+        },
+        ZipArchive, ZipFile,
+    },
+    futures_lite::io::{AsyncRead, AsyncSeek, AsyncWrite, AsyncWriteExt},
+};
+
+pub mod dir;
+pub mod policy;
+
+use policy::CompressionPolicy;
+
+const VERSION_MADE_BY: u16 = 20;
+const VERSION_NEEDED: u16 = 20;
+
+#[derive(Clone, Debug)]
+pub struct FileOptions {
+    compression: Compression,
+    level: Option<i32>,
+    last_modified: ZipDateTime,
+    permissions: u32,
+    comment: Option<String>,
+}
+
+pub struct ZipWriter<W> {
+    writer: W,
+    entries: Vec<ZipEntry>,
+    offset: u64,
+    comment: Option<String>,
+    epoch: Option<ZipDateTime>,
+    pending: Vec<(ZipEntry, Vec<u8>)>,
+    policy: Option<CompressionPolicy>,
+}
+
+impl Default for FileOptions {
+    fn default() -> Self {
+        Self {
+            compression: if cfg!(feature = "deflate") {
+                Compression::Deflate
+            } else {
+                Compression::Stored
+            },
+            level: None,
+            last_modified: ZipDateTime::default(),
+            permissions: 0o644,
+            comment: None,
+        }
+    }
+}
+
+impl FileOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn compression_level(mut self, level: i32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    pub fn last_modified(mut self, datetime: ZipDateTime) -> Self {
+        self.last_modified = datetime;
+        self
+    }
+
+    pub fn unix_permissions(mut self, mode: u32) -> Self {
+        self.permissions = mode & 0o777;
+        self
+    }
+
+    pub fn comment<S>(mut self, comment: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    fn attributes(&self, kind: u32) -> Attributes {
+        Attributes::from((
+            (kind | self.permissions) << 16,
+            &AttributeCompatibility::Unix,
+        ))
+    }
+}
+
+impl<W> ZipWriter<W> {
+    pub fn len(&self) -> usize {
+        self.entries.len() + self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.epoch.is_some()
+    }
+
+    pub fn set_comment<S>(&mut self, comment: S)
+    where
+        S: Into<String>,
+    {
+        self.comment = Some(comment.into());
+    }
+
+    pub fn set_compression_policy(&mut self, policy: CompressionPolicy) {
+        self.policy = Some(policy);
+    }
+}
+
+impl<W> ZipWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            entries: Vec::new(),
+            offset: 0,
+            comment: None,
+            epoch: None,
+            pending: Vec::new(),
+            policy: None,
+        }
+    }
+
+    /// Creates a writer whose output depends only on the entry names, contents and
+    /// compression methods. Entries are buffered and written sorted by name when
+    /// finishing, with timestamps set to `SOURCE_DATE_EPOCH` (or 1980-01-01),
+    /// permissions reduced to 0o644/0o755 and no host-specific metadata.
+    pub fn deterministic(writer: W) -> Self {
+        let epoch = std::env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|seconds| seconds.trim().parse().ok())
+            .map(ZipDateTime::from_unix_timestamp)
+            .unwrap_or_default();
+        Self {
+            epoch: Some(epoch),
+            ..Self::new(writer)
+        }
+    }
+
+    pub async fn create_dir<S>(&mut self, name: S, options: FileOptions) -> ZipResult<()>
+    where
+        S: AsRef<str>,
+    {
+        let entry = new_entry(name.as_ref(), options.attributes(0o040000), &options)?;
+        self.write_entry(entry, &[]).await
+    }
+
+    pub async fn write_file<S>(
+        &mut self,
+        name: S,
+        data: &[u8],
+        options: FileOptions,
+    ) -> ZipResult<()>
+    where
+        S: AsRef<str>,
+    {
+        let mut entry = new_entry(name.as_ref(), options.attributes(0o100000), &options)?;
+        let (compression, level) = match &self.policy {
+            Some(policy) => policy.select(name.as_ref(), data),
+            None => (options.compression, options.level),
+        };
+        let mut compressed = compression.compress(data, level).await?;
+        entry.compression = compression;
+        if self.policy.is_some() && compressed.len() >= data.len() {
+            entry.compression = Compression::Stored;
+            compressed = data.to_vec();
+        }
+
+        entry.crc32 = crc32fast::hash(data);
+        entry.compressed_size = compressed.len() as u64;
+        entry.uncompressed_size = data.len() as u64;
+        self.write_entry(entry, &compressed).await
+    }
+
+    /// Stores a symbolic link whose target is kept verbatim as the entry data.
+    pub async fn create_symlink<S, T>(
+        &mut self,
+        name: S,
+        target: T,
+        options: FileOptions,
+    ) -> ZipResult<()>
+    where
+        S: AsRef<str>,
+        T: AsRef<str>,
+    {
+        let options = options
+            .compression(Compression::Stored)
+            .unix_permissions(0o777);
+        let target = target.as_ref().as_bytes();
+        let mut entry = new_entry(name.as_ref(), options.attributes(0o120000), &options)?;
+
+        entry.crc32 = crc32fast::hash(target);
+        entry.compressed_size = target.len() as u64;
+        entry.uncompressed_size = entry.compressed_size;
+        self.write_entry(entry, target).await
+    }
+
+    pub async fn copy_file<R>(&mut self, archive: &mut ZipArchive<R>, index: usize) -> ZipResult<()>
+    where
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        let (entry, data) = archive.read_raw(index).await?;
+        self.write_entry(entry, &data).await
+    }
+
+    pub(crate) async fn copy_raw(&mut self, entry: &ZipEntry, data: &[u8]) -> ZipResult<()> {
+        self.write_entry(entry.clone(), data).await
+    }
+
+    pub async fn finish(mut self) -> ZipResult<W> {
+        let mut pending = std::mem::take(&mut self.pending);
+        pending.sort_by_cached_key(|(entry, _data)| entry_name(entry));
+        for (entry, data) in pending {
+            self.emit_entry(entry, &data).await?;
+        }
+
+        let offset = self.offset;
+        for entry in &self.entries {
+            let record = central_record(entry);
+            self.writer.write_all(&record).await?;
+            self.offset += record.len() as u64;
+        }
+
+        let count = u16::try_from(self.entries.len())
+            .map_err(|_| ZipError::FeatureNotSupported("Writing more than 65535 entries".into()))?;
+        let comment = self.comment.unwrap_or_default();
+        let comment_length = u16::try_from(comment.len()).map_err(|_| {
+            ZipError::InvalidArchive("Archive comment is longer than 65535 bytes".into())
+        })?;
+
+        let mut record = Vec::with_capacity(22 + comment.len());
+        record.extend_from_slice(&Zip32CentralDirectoryEndRecord::SIGNATURE.to_le_bytes());
+        record.extend_from_slice(&[0; 4]);
+        record.extend_from_slice(&count.to_le_bytes());
+        record.extend_from_slice(&count.to_le_bytes());
+        record.extend_from_slice(&to_u32(self.offset - offset)?.to_le_bytes());
+        record.extend_from_slice(&to_u32(offset)?.to_le_bytes());
+        record.extend_from_slice(&comment_length.to_le_bytes());
+        record.extend_from_slice(comment.as_bytes());
+        self.writer.write_all(&record).await?;
+        self.writer.flush().await?;
+        Ok(self.writer)
+    }
+
+    async fn write_entry(&mut self, mut entry: ZipEntry, data: &[u8]) -> ZipResult<()> {
+        let comment = entry.comment.as_deref().unwrap_or_default();
+        if entry_name(&entry).len() > u16::MAX as usize || comment.len() > u16::MAX as usize {
+            Err(ZipError::InvalidArchive(
+                "Entry names and comments must be shorter than 65536 bytes".into(),
+            ))?
+        }
+        // Headers only have room for 32-bit sizes.
+        to_u32(entry.compressed_size)?;
+        to_u32(entry.uncompressed_size)?;
+
+        match self.epoch {
+            Some(epoch) => {
+                normalize(&mut entry, epoch);
+                self.pending.push((entry, data.to_vec()));
+                Ok(())
+            }
+            None => self.emit_entry(entry, data).await,
+        }
+    }
+
+    async fn emit_entry(&mut self, mut entry: ZipEntry, data: &[u8]) -> ZipResult<()> {
+        entry.disk_start = 0;
+        entry.file_header_offset = to_u32(self.offset)? as u64;
+
+        let header = local_header(&entry);
+        self.writer.write_all(&header).await?;
+        self.writer.write_all(data).await?;
+        self.offset += (header.len() + data.len()) as u64;
+
+        if entry.flags.data_drescriptor {
+            let mut descriptor = Vec::with_capacity(16);
+            descriptor.extend_from_slice(&DATA_DESCRIPTOR_SIGNATURE.to_le_bytes());
+            descriptor.extend_from_slice(&entry.crc32.to_le_bytes());
+            descriptor.extend_from_slice(&(entry.compressed_size as u32).to_le_bytes());
+            descriptor.extend_from_slice(&(entry.uncompressed_size as u32).to_le_bytes());
+            self.writer.write_all(&descriptor).await?;
+            self.offset += descriptor.len() as u64;
+        }
+
+        self.entries.push(entry);
+        Ok(())
+    }
+}
+
+fn new_entry(name: &str, attributes: Attributes, options: &FileOptions) -> ZipResult<ZipEntry> {
+    let mut file_name = ZipPath::from(name.replace('\\', "/"));
+    file_name.sanitize();
+    if file_name.is_empty() {
+        Err(ZipError::InvalidArchive(
+            format!("{name:?} is not a valid entry name").into(),
+        ))?
+    }
+    file_name.update(&attributes);
+
+    let flags = GeneralPurposeFlag {
+        encrypted: false,
+        compression_options: 0,
+        data_drescriptor: false,
+        utf8_required: !name.is_ascii(),
+        central_directory_encrypted: false,
+    };
+    let compression = if attributes.directory {
+        Compression::Stored
+    } else {
+        options.compression
+    };
+
+    Ok(ZipEntry {
+        version_made_by: AttributeCompatibility::Unix,
+        version_needed: VERSION_NEEDED,
+        flags,
+        compression,
+        last_mod_datetime: options.last_modified,
+        crc32: 0,
+        compressed_size: 0,
+        uncompressed_size: 0,
+        disk_start: 0,
+        internal_attribute: 0,
+        external_attribute: attributes,
+        file_header_offset: 0,
+        comment: options.comment.clone(),
+        extra_field: None,
+        file_name,
+    })
+}
+
+fn normalize(entry: &mut ZipEntry, epoch: ZipDateTime) {
+    let attributes = &entry.external_attribute;
+    let mode = if attributes.symbolic {
+        0o120777
+    } else if attributes.directory {
+        0o040755
+    } else if attributes.owner.execute {
+        0o100755
+    } else {
+        0o100644
+    };
+
+    entry.version_made_by = AttributeCompatibility::Unix;
+    entry.external_attribute = Attributes::from((mode << 16, &AttributeCompatibility::Unix));
+    entry.file_name.update(&entry.external_attribute);
+    entry.internal_attribute = 0;
+    entry.last_mod_datetime = epoch;
+    entry.extra_field = None;
+}
+
+fn entry_name(entry: &ZipEntry) -> Vec<u8> {
+    let mut name: Vec<u8> = entry
+        .file_name
+        .raw_name()
+        .iter()
+        .map(|&byte| if byte == b'\\' { b'/' } else { byte })
+        .collect();
+    if entry.is_dir() && !name.ends_with(b"/") {
+        name.push(b'/');
+    }
+    name
+}
+
+fn local_header(entry: &ZipEntry) -> Vec<u8> {
+    let name = entry_name(entry);
+    let datetime: [u8; 4] = entry.last_mod_datetime.into();
+
+    let mut header = Vec::with_capacity(30 + name.len());
+    header.extend_from_slice(&ZipFile::SIGNATURE.to_le_bytes());
+    header.extend_from_slice(&entry.version_needed.to_le_bytes());
+    header.extend_from_slice(&u16::from(&entry.flags).to_le_bytes());
+    header.extend_from_slice(&u16::from(entry.compression).to_le_bytes());
+    header.extend_from_slice(&datetime);
+    header.extend_from_slice(&entry.crc32.to_le_bytes());
+    header.extend_from_slice(&(entry.compressed_size as u32).to_le_bytes());
+    header.extend_from_slice(&(entry.uncompressed_size as u32).to_le_bytes());
+    header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    header.extend_from_slice(&0u16.to_le_bytes());
+    header.extend_from_slice(&name);
+    header
+}
+
+fn central_record(entry: &ZipEntry) -> Vec<u8> {
+    let name = entry_name(entry);
+    let comment = entry.comment.as_deref().unwrap_or_default();
+    let datetime: [u8; 4] = entry.last_mod_datetime.into();
+    let version_made_by = VERSION_MADE_BY | u16::from(entry.version_made_by.clone()) << 8;
+
+    let mut record = Vec::with_capacity(46 + name.len() + comment.len());
+    record.extend_from_slice(&ZipEntry::SIGNATURE.to_le_bytes());
+    record.extend_from_slice(&version_made_by.to_le_bytes());
+    record.extend_from_slice(&entry.version_needed.to_le_bytes());
+    record.extend_from_slice(&u16::from(&entry.flags).to_le_bytes());
+    record.extend_from_slice(&u16::from(entry.compression).to_le_bytes());
+    record.extend_from_slice(&datetime);
+    record.extend_from_slice(&entry.crc32.to_le_bytes());
+    record.extend_from_slice(&(entry.compressed_size as u32).to_le_bytes());
+    record.extend_from_slice(&(entry.uncompressed_size as u32).to_le_bytes());
+    record.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    record.extend_from_slice(&0u16.to_le_bytes());
+    record.extend_from_slice(&(comment.len() as u16).to_le_bytes());
+    record.extend_from_slice(&entry.disk_start.to_le_bytes());
+    record.extend_from_slice(&entry.internal_attribute.to_le_bytes());
+    record.extend_from_slice(&u32::from(&entry.external_attribute).to_le_bytes());
+    record.extend_from_slice(&(entry.file_header_offset as u32).to_le_bytes());
+    record.extend_from_slice(&name);
+    record.extend_from_slice(comment.as_bytes());
+    record
+}
+
+fn to_u32(value: u64) -> ZipResult<u32> {
+    u32::try_from(value).map_err(|_| ZipError::FeatureNotSupported("Writing Zip64 archives".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "deflate")]
+    use super::policy::CompressionPolicy;
+    use {
+        super::{
+            dir::{DirOptions, Symlinks},
+            FileOptions, ZipWriter,
+        },
+        crate::{
+            compression::Compression, datetime::ZipDateTime, error::ZipError, glob::Pattern,
+            testing::TempDir, ZipArchive,
+        },
+        futures_lite::{future::block_on, io::Cursor},
+        std::fs,
+    };
+
+    #[test]
+    #[cfg(feature = "deflate")]
+    fn write_and_read_back() {
+        block_on(async {
+            let modified = ZipDateTime {
+                year: 2024,
+                month: 7,
+                day: 14,
+                hour: 9,
+                minute: 41,
+                second: 30,
+            };
+            let text = "compress me ".repeat(64);
+
+            let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+            writer.set_comment("built by a test");
+            writer
+                .create_dir("docs", FileOptions::new().unix_permissions(0o755))
+                .await
+                .unwrap();
+            writer
+                .write_file("docs/readme.txt", text.as_bytes(), FileOptions::new())
+                .await
+                .unwrap();
+            writer
+                .write_file(
+                    "bin/run.sh",
+                    b"#!/bin/sh\n",
+                    FileOptions::new()
+                        .compression(Compression::Stored)
+                        .last_modified(modified)
+                        .unix_permissions(0o755),
+                )
+                .await
+                .unwrap();
+            let archive = writer.finish().await.unwrap().into_inner();
+
+            let mut zip = ZipArchive::new(Cursor::new(archive)).await.unwrap();
+            assert_eq!(zip.comment().as_deref(), Some("built by a test"));
+            assert!(zip.entry_by_name("docs/").unwrap().is_dir());
+
+            let readme = zip.entry_by_name("docs/readme.txt").unwrap();
+            assert_eq!(readme.compression(), Compression::Deflate);
+            assert!(readme.compressed_size() < readme.uncompressed_size());
+
+            let script = zip.entry_by_name("bin/run.sh").unwrap();
+            assert_eq!(script.last_modified(), modified);
+            assert!(script.attributes().owner.execute);
+            assert!(script.attributes().other.read);
+            assert!(!script.attributes().other.write);
+
+            let file = zip.file_by_name("docs/readme.txt").await.unwrap();
+            assert_eq!(file.extract().await.unwrap(), text.as_bytes());
+            assert!(zip.verify().await.is_ok());
+        })
+    }
+
+    #[test]
+    fn write_deterministic_archive() {
+        block_on(async {
+            let modified = ZipDateTime {
+                year: 2024,
+                month: 7,
+                day: 14,
+                hour: 9,
+                minute: 41,
+                second: 30,
+            };
+            let files: [(&str, &[u8], u32); 3] = [
+                ("b.txt", b"bravo", 0o600),
+                ("a/run.sh", b"#!/bin/sh\n", 0o700),
+                ("a.txt", b"alpha", 0o664),
+            ];
+
+            let mut archives = Vec::new();
+            for (reverse, last_modified) in [(false, modified), (true, ZipDateTime::default())] {
+                let mut writer = ZipWriter::deterministic(Cursor::new(Vec::new()));
+                assert!(writer.is_deterministic());
+                let options = FileOptions::new().last_modified(last_modified);
+                let mut order: Vec<_> = files.iter().collect();
+                if reverse {
+                    order.reverse();
+                }
+                for (name, data, mode) in order {
+                    let options = options.clone().unix_permissions(*mode);
+                    writer.write_file(name, data, options).await.unwrap();
+                }
+                writer.create_dir("a", options.clone()).await.unwrap();
+                assert_eq!(writer.len(), 4);
+                archives.push(writer.finish().await.unwrap().into_inner());
+            }
+            assert_eq!(archives[0], archives[1]);
+
+            let zip = ZipArchive::new(Cursor::new(archives.remove(0)))
+                .await
+                .unwrap();
+            let names: Vec<_> = zip.entries().map(|entry| entry.name().clone()).collect();
+            assert_eq!(
+                names,
+                [
+                    "a.txt".into(),
+                    "a/".into(),
+                    "a/run.sh".into(),
+                    "b.txt".into()
+                ]
+            );
+
+            let epoch = zip.entry_by_index(0).unwrap().last_modified();
+            assert!(zip.entries().all(|entry| entry.last_modified() == epoch));
+            let script = zip.entry_by_name("a/run.sh").unwrap().attributes();
+            assert!(script.owner.execute && script.group.execute && !script.group.write);
+            let text = zip.entry_by_name("b.txt").unwrap().attributes();
+            assert!(text.other.read && !text.owner.execute);
+
+            assert_eq!(ZipDateTime::from_unix_timestamp(0), ZipDateTime::default());
+            assert_eq!(
+                ZipDateTime::from_unix_timestamp(1_700_000_000),
+                ZipDateTime {
+                    year: 2023,
+                    month: 11,
+                    day: 14,
+                    hour: 22,
+                    minute: 13,
+                    second: 20,
+                }
+            );
+        })
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn add_directory_tree() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let root = TempDir::new("add-dir");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(
+            root.join(".gitignore"),
+            "# build output\ntarget/\n*.log\n!keep.log\n",
+        )
+        .unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("src/build.log"), "noise").unwrap();
+        fs::write(root.join("src/keep.log"), "signal").unwrap();
+        fs::write(root.join("target/out.bin"), [0; 16]).unwrap();
+        fs::write(root.join("notes.tmp"), "scratch").unwrap();
+        fs::write(root.join("run.sh"), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(root.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        symlink("src/main.rs", root.join("link")).unwrap();
+        symlink("missing.txt", root.join("dangling")).unwrap();
+
+        let build = |options: DirOptions| {
+            block_on(async {
+                let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+                let count = writer.add_dir(&*root, &options).await.unwrap();
+                assert_eq!(count, writer.len());
+                let archive = writer.finish().await.unwrap().into_inner();
+                ZipArchive::new(Cursor::new(archive)).await.unwrap()
+            })
+        };
+
+        let options = DirOptions::new()
+            .prefix("pkg/")
+            .ignore_file(".gitignore")
+            .exclude(Pattern::new("**/*.tmp").unwrap());
+        let mut zip = build(options.clone());
+        let names: Vec<_> = zip.entries().map(|entry| entry.name().clone()).collect();
+        assert_eq!(
+            names,
+            [
+                "pkg/.gitignore".into(),
+                "pkg/dangling".into(),
+                "pkg/link".into(),
+                "pkg/run.sh".into(),
+                "pkg/src/".into(),
+                "pkg/src/keep.log".into(),
+                "pkg/src/main.rs".into(),
+            ]
+        );
+        assert!(
+            zip.entry_by_name("pkg/run.sh")
+                .unwrap()
+                .attributes()
+                .owner
+                .execute
+        );
+        assert!(
+            zip.entry_by_name("pkg/src/main.rs")
+                .unwrap()
+                .last_modified()
+                .year
+                >= 2024
+        );
+        let link = zip.entry_by_name("pkg/link").unwrap();
+        assert!(link.attributes().symbolic);
+        block_on(async {
+            let link = zip.file_by_name("pkg/link").await.unwrap();
+            assert_eq!(link.extract().await.unwrap(), b"src/main.rs");
+        });
+
+        let mut zip = build(options.symlinks(Symlinks::Follow));
+        assert!(!zip.entry_by_name("pkg/link").unwrap().attributes().symbolic);
+        assert!(
+            zip.entry_by_name("pkg/dangling")
+                .unwrap()
+                .attributes()
+                .symbolic
+        );
+        block_on(async {
+            let link = zip.file_by_name("pkg/link").await.unwrap();
+            assert_eq!(link.extract().await.unwrap(), b"fn main() {}\n");
+        });
+
+        let zip = build(DirOptions::new().include(Pattern::new("**/*.rs").unwrap()));
+        let names: Vec<_> = zip.entries().map(|entry| entry.name().clone()).collect();
+        assert_eq!(names, ["src/main.rs".into()]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn follow_links_to_directories() {
+        use std::os::unix::fs::symlink;
+
+        let root = TempDir::new("follow-links");
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::write(root.join("b/file.txt"), "bravo").unwrap();
+        symlink("b", root.join("a_link")).unwrap();
+        symlink("b", root.join("c_link")).unwrap();
+        symlink("..", root.join("b/up")).unwrap();
+
+        let options = DirOptions::new().symlinks(Symlinks::Follow);
+        let zip = block_on(async {
+            let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+            writer.add_dir(&*root, &options).await.unwrap();
+            let archive = writer.finish().await.unwrap().into_inner();
+            ZipArchive::new(Cursor::new(archive)).await.unwrap()
+        });
+        let names: Vec<_> = zip.entries().map(|entry| entry.name().clone()).collect();
+        assert_eq!(
+            names,
+            [
+                "a_link/".into(),
+                "a_link/file.txt".into(),
+                "b/".into(),
+                "b/file.txt".into(),
+                "c_link/".into(),
+                "c_link/file.txt".into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn refuse_disabled_codecs() {
+        block_on(async {
+            let codecs = [
+                (Compression::Deflate, cfg!(feature = "deflate")),
+                (Compression::Bzip2, cfg!(feature = "bzip2")),
+                (Compression::Zstd, cfg!(feature = "zstd")),
+                (Compression::Xz, cfg!(feature = "xz")),
+                (Compression::Ppmd, cfg!(feature = "ppmd")),
+            ];
+            for (compression, enabled) in codecs {
+                let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+                let options = FileOptions::new().compression(compression);
+                let result = writer.write_file("a.txt", b"alpha", options).await;
+                match enabled {
+                    true => assert!(result.is_ok(), "{compression:?}: {result:?}"),
+                    false => assert!(
+                        matches!(result, Err(ZipError::CompressionDisabled(method)) if method == compression)
+                    ),
+                }
+            }
+        })
+    }
+
+    #[test]
+    #[cfg(feature = "deflate")]
+    fn write_with_compression_policy() {
+        block_on(async {
+            let text = "compress me ".repeat(64);
+            let mut jpeg = b"\xff\xd8\xff\xe0".to_vec();
+            jpeg.extend_from_slice(text.as_bytes());
+            let mut state = 0x2545_f491_u32;
+            let noise: Vec<u8> = (0..512)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    state as u8
+                })
+                .collect();
+
+            let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+            writer.set_compression_policy(
+                CompressionPolicy::new(Compression::Deflate)
+                    .level(9)
+                    .store_extension(".PAK"),
+            );
+            let options = FileOptions::new().compression(Compression::Stored);
+            for (name, data) in [
+                ("notes.txt", text.as_bytes()),
+                ("photo.JPG", text.as_bytes()),
+                ("assets.pak", text.as_bytes()),
+                ("camera.bin", &jpeg),
+                ("noise.bin", &noise),
+            ] {
+                writer
+                    .write_file(name, data, options.clone())
+                    .await
+                    .unwrap();
+            }
+            let archive = writer.finish().await.unwrap().into_inner();
+
+            let mut zip = ZipArchive::new(Cursor::new(archive)).await.unwrap();
+            let compression = |name| zip.entry_by_name(name).unwrap().compression();
+            assert_eq!(compression("notes.txt"), Compression::Deflate);
+            assert_eq!(compression("photo.JPG"), Compression::Stored);
+            assert_eq!(compression("assets.pak"), Compression::Stored);
+            assert_eq!(compression("camera.bin"), Compression::Stored);
+            assert_eq!(compression("noise.bin"), Compression::Stored);
+
+            let file = zip.file_by_name("noise.bin").await.unwrap();
+            assert_eq!(file.extract().await.unwrap(), noise);
+            let file = zip.file_by_name("notes.txt").await.unwrap();
+            assert_eq!(file.extract().await.unwrap(), text.as_bytes());
+        })
+    }
+}
@@ -0,0 +1,326 @@
+use {
+    super::{FileOptions, ZipWriter},
+    crate::{
+        datetime::ZipDateTime,
+        error::{ZipError, ZipResult},
+        glob::Pattern,
+    },
+    futures_lite::io::AsyncWrite,
+    std::{
+        ffi::OsString,
+        fs::{self, Metadata},
+        io::ErrorKind,
+        path::{Path, PathBuf},
+        time::UNIX_EPOCH,
+    },
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Symlinks {
+    /// Links are added as symlink entries pointing at their original target.
+    #[default]
+    Store,
+    /// Links are resolved and their target is added in their place. Links
+    /// whose target does not exist are stored.
+    Follow,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DirOptions {
+    file: FileOptions,
+    prefix: String,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    ignore_files: Vec<String>,
+    symlinks: Symlinks,
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    base: String,
+    pattern: Pattern,
+    negated: bool,
+    dir_only: bool,
+}
+
+enum Source {
+    Dir,
+    File(PathBuf),
+    Symlink(String),
+}
+
+struct Item {
+    name: String,
+    source: Source,
+    options: FileOptions,
+}
+
+struct Walker<'a> {
+    options: &'a DirOptions,
+    /// Canonical paths of the directories being walked, from the root down.
+    ancestors: Vec<PathBuf>,
+    items: Vec<Item>,
+}
+
+impl DirOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Options applied to every entry. Permissions and modification times are
+    /// taken from the filesystem instead.
+    pub fn file_options(mut self, options: FileOptions) -> Self {
+        self.file = options;
+        self
+    }
+
+    /// Directory inside the archive that receives the tree.
+    pub fn prefix<S>(mut self, prefix: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Once any include pattern is set, only matching paths are added.
+    /// Directories are still descended into.
+    pub fn include(mut self, pattern: Pattern) -> Self {
+        self.include.push(pattern);
+        self
+    }
+
+    /// Skips matching paths, and everything below them for directories.
+    pub fn exclude(mut self, pattern: Pattern) -> Self {
+        self.exclude.push(pattern);
+        self
+    }
+
+    /// Reads `.gitignore`-style rules from files with this name in every
+    /// directory of the tree.
+    pub fn ignore_file<S>(mut self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.ignore_files.push(name.into());
+        self
+    }
+
+    pub fn symlinks(mut self, symlinks: Symlinks) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+}
+
+impl<W> ZipWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    /// Recursively adds the contents of `root` with paths relative to it, in
+    /// file name order. Returns the number of entries written.
+    pub async fn add_dir<P>(&mut self, root: P, options: &DirOptions) -> ZipResult<usize>
+    where
+        P: AsRef<Path>,
+    {
+        let items = Walker::new(options).walk(root.as_ref())?;
+        let prefix = options.prefix.trim_matches('/');
+        for item in &items {
+            let name = match prefix {
+                "" => item.name.clone(),
+                prefix => format!("{prefix}/{}", item.name),
+            };
+            let options = item.options.clone();
+            match &item.source {
+                Source::Dir => self.create_dir(name, options).await?,
+                Source::File(path) => self.write_file(name, &fs::read(path)?, options).await?,
+                Source::Symlink(target) => self.create_symlink(name, target, options).await?,
+            }
+        }
+        Ok(items.len())
+    }
+}
+
+impl<'a> Walker<'a> {
+    fn new(options: &'a DirOptions) -> Self {
+        Self {
+            options,
+            ancestors: Vec::new(),
+            items: Vec::new(),
+        }
+    }
+
+    fn walk(mut self, root: &Path) -> ZipResult<Vec<Item>> {
+        if !fs::metadata(root)?.is_dir() {
+            Err(ZipError::InvalidArchive(
+                format!("{root:?} is not a directory").into(),
+            ))?
+        }
+        self.ancestors.push(fs::canonicalize(root)?);
+        self.walk_dir(root, "", &[])?;
+        Ok(self.items)
+    }
+
+    fn walk_dir(&mut self, dir: &Path, relative: &str, rules: &[Rule]) -> ZipResult<()> {
+        let mut rules = rules.to_vec();
+        for name in &self.options.ignore_files {
+            match fs::read_to_string(dir.join(name)) {
+                Ok(text) => rules.extend(parse_ignore(&text, relative)?),
+                Err(error) if error.kind() == ErrorKind::NotFound => {}
+                Err(error) => Err(error)?,
+            }
+        }
+
+        let mut children = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+        children.sort_by_key(|child| child.file_name());
+        for child in children {
+            let path = child.path();
+            let name = match relative {
+                "" => utf8(child.file_name())?,
+                relative => format!("{relative}/{}", utf8(child.file_name())?),
+            };
+
+            let mut metadata = fs::symlink_metadata(&path)?;
+            if metadata.is_symlink() && self.options.symlinks == Symlinks::Follow {
+                match fs::metadata(&path) {
+                    Ok(target) => metadata = target,
+                    Err(error) if error.kind() == ErrorKind::NotFound => {}
+                    Err(error) => Err(error)?,
+                }
+            }
+            if self.excluded(&name, &rules, metadata.is_dir()) {
+                continue;
+            }
+
+            let options = file_options(self.options.file.clone(), &metadata);
+            let source = if metadata.is_symlink() {
+                Source::Symlink(utf8(fs::read_link(&path)?.into_os_string())?)
+            } else if metadata.is_dir() {
+                // Following links can lead back to a directory being walked.
+                let canonical = fs::canonicalize(&path)?;
+                if self.ancestors.contains(&canonical) {
+                    continue;
+                }
+                if self.included(&name) {
+                    self.items.push(Item {
+                        name: name.clone(),
+                        source: Source::Dir,
+                        options,
+                    });
+                }
+                self.ancestors.push(canonical);
+                self.walk_dir(&path, &name, &rules)?;
+                self.ancestors.pop();
+                continue;
+            } else {
+                Source::File(path)
+            };
+
+            if self.included(&name) {
+                self.items.push(Item {
+                    name,
+                    source,
+                    options,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn included(&self, name: &str) -> bool {
+        self.options.include.is_empty()
+            || self
+                .options
+                .include
+                .iter()
+                .any(|pattern| pattern.matches(name))
+    }
+
+    fn excluded(&self, name: &str, rules: &[Rule], is_dir: bool) -> bool {
+        if self
+            .options
+            .exclude
+            .iter()
+            .any(|pattern| pattern.matches(name))
+        {
+            return true;
+        }
+
+        // As with git, the last matching rule wins.
+        rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                let name = match rule.base.as_str() {
+                    "" => name,
+                    base => &name[base.len() + 1..],
+                };
+                (is_dir || !rule.dir_only) && rule.pattern.matches(name)
+            })
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+fn parse_ignore(text: &str, base: &str) -> ZipResult<Vec<Rule>> {
+    let mut rules = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let line = line.strip_prefix('\\').unwrap_or(line);
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        // Patterns without an inner slash match at any depth below the ignore file.
+        let pattern = match line.strip_prefix('/') {
+            Some(anchored) => anchored.to_string(),
+            None if line.contains('/') => line.to_string(),
+            None => format!("**/{line}"),
+        };
+
+        rules.push(Rule {
+            base: base.to_string(),
+            pattern: Pattern::new(&pattern)?,
+            negated,
+            dir_only,
+        });
+    }
+    Ok(rules)
+}
+
+fn file_options(options: FileOptions, metadata: &Metadata) -> FileOptions {
+    let options = match metadata.modified() {
+        Ok(modified) => {
+            let seconds = match modified.duration_since(UNIX_EPOCH) {
+                Ok(elapsed) => elapsed.as_secs() as i64,
+                Err(error) => -(error.duration().as_secs() as i64),
+            };
+            options.last_modified(ZipDateTime::from_unix_timestamp(seconds))
+        }
+        Err(_) => options,
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        options.unix_permissions(metadata.permissions().mode())
+    }
+    #[cfg(not(unix))]
+    match metadata.is_dir() {
+        true => options.unix_permissions(0o755),
+        false if metadata.permissions().readonly() => options.unix_permissions(0o444),
+        false => options,
+    }
+}
+
+fn utf8(name: OsString) -> ZipResult<String> {
+    name.into_string().map_err(|name| {
+        ZipError::InvalidArchive(format!("{name:?} is not a valid UTF-8 entry name").into())
+    })
+}
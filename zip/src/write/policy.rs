@@ -0,0 +1,93 @@
+use {
+    super::FileOptions,
+    crate::compression::Compression,
+    std::{collections::HashSet, path::Path},
+};
+
+const STORED_EXTENSIONS: &[&str] = &[
+    "7z", "apk", "avi", "br", "bz2", "docx", "flac", "gif", "gz", "heic", "jar", "jpeg", "jpg",
+    "lz4", "lzma", "m4a", "m4v", "mkv", "mov", "mp3", "mp4", "ogg", "opus", "png", "pptx", "rar",
+    "tgz", "webm", "webp", "whl", "woff", "woff2", "xlsx", "xz", "zip", "zst",
+];
+
+/// Magic numbers of already compressed formats, with their offset.
+const SIGNATURES: &[(usize, &[u8])] = &[
+    (0, b"PK\x03\x04"),
+    (0, b"\x1f\x8b"),
+    (0, b"BZh"),
+    (0, b"\xfd7zXZ\x00"),
+    (0, b"\x28\xb5\x2f\xfd"),
+    (0, b"7z\xbc\xaf\x27\x1c"),
+    (0, b"Rar!\x1a\x07"),
+    (0, b"\xff\xd8\xff"),
+    (0, b"\x89PNG\r\n\x1a\n"),
+    (0, b"GIF8"),
+    (0, b"OggS"),
+    (0, b"fLaC"),
+    (0, b"ID3"),
+    (0, b"wOF2"),
+    (4, b"ftyp"),
+    (8, b"WEBP"),
+];
+
+/// Chooses the compression of each file written through a [`ZipWriter`],
+/// overriding the method from its [`FileOptions`]. Entries that look already
+/// compressed, or that would not shrink, are stored.
+///
+/// [`ZipWriter`]: super::ZipWriter
+#[derive(Clone, Debug)]
+pub struct CompressionPolicy {
+    compression: Compression,
+    level: Option<i32>,
+    extensions: HashSet<String>,
+}
+
+impl Default for CompressionPolicy {
+    fn default() -> Self {
+        Self::new(FileOptions::default().compression)
+    }
+}
+
+impl CompressionPolicy {
+    pub fn new(compression: Compression) -> Self {
+        Self {
+            compression,
+            level: None,
+            extensions: STORED_EXTENSIONS
+                .iter()
+                .map(|ext| ext.to_string())
+                .collect(),
+        }
+    }
+
+    pub fn level(mut self, level: i32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Stores files with this extension without trying to compress them.
+    pub fn store_extension<S>(mut self, extension: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        let extension = extension.as_ref().trim_start_matches('.');
+        self.extensions.insert(extension.to_ascii_lowercase());
+        self
+    }
+
+    pub(crate) fn select(&self, name: &str, data: &[u8]) -> (Compression, Option<i32>) {
+        let extension = Path::new(name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+        let known = extension.is_some_and(|extension| self.extensions.contains(&extension));
+        let sniffed = SIGNATURES.iter().any(|(offset, magic)| {
+            data.get(*offset..)
+                .is_some_and(|data| data.starts_with(magic))
+        });
+
+        match known || sniffed {
+            true => (Compression::Stored, None),
+            false => (self.compression, self.level),
+        }
+    }
+}